@@ -0,0 +1,40 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+
+// Byte offset of `arbiter` in an Escrow account: discriminator (8) + escrow_id (8)
+// + initiator (32) + recipient (32). Mirrors ESCROW_ARBITER_OFFSET in the program.
+export const ESCROW_ARBITER_OFFSET = 80;
+
+// Byte offset of the one-byte `status` discriminant: arbiter offset + arbiter (32) + amount (8)
+// + released_amount (8) + deal_type (1). Mirrors ESCROW_STATUS_OFFSET in the program.
+export const ESCROW_STATUS_OFFSET = 129;
+
+// EscrowStatus discriminants, in declaration order
+export const ESCROW_STATUS_DISPUTED = 5;
+
+// getProgramAccounts filter matching escrows assigned to the given arbiter
+export const arbiterFilter = (arbiter: anchor.web3.PublicKey) => ({
+  memcmp: { offset: ESCROW_ARBITER_OFFSET, bytes: arbiter.toBase58() },
+});
+
+// getProgramAccounts filter matching escrows in the given status
+export const statusFilter = (status: number) => ({
+  memcmp: {
+    offset: ESCROW_STATUS_OFFSET,
+    bytes: anchor.utils.bytes.bs58.encode(Buffer.from([status])),
+  },
+});
+
+// Every escrow assigned to the given arbiter, e.g. for a dispute dashboard
+export const fetchEscrowsByArbiter = (
+  program: Program<Escrow>,
+  arbiter: anchor.web3.PublicKey
+) => program.account.escrow.all([arbiterFilter(arbiter)]);
+
+// Disputed escrows awaiting the given arbiter's settlement
+export const fetchDisputedEscrowsByArbiter = (
+  program: Program<Escrow>,
+  arbiter: anchor.web3.PublicKey
+) =>
+  program.account.escrow.all([arbiterFilter(arbiter), statusFilter(ESCROW_STATUS_DISPUTED)]);
@@ -1,12 +1,21 @@
+// Instruction handlers take their terms as arguments, and the generated wrappers mirror them
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::{AssociatedToken},
-    token::{self, Token, TokenAccount, Mint, Transfer},
+    token,
+    token_2022::spl_token_2022,
+    token_interface::{self, TokenInterface, TokenAccount, Mint, Transfer},
 };
 use anchor_lang::solana_program::{
-    program::invoke,
+    hash::{hash, hashv},
+    instruction::{AccountMeta, Instruction},
+    program::{invoke, invoke_signed},
+    program_option::COption,
     system_instruction,
 };
+use pyth_sdk_solana::{state::SolanaPriceAccount, Price};
 
 declare_id!("7UMWhVX2ZpqLa1iWqUM1tJz6LjRYWQ1oheZpuMtQKxs1");
 
@@ -14,35 +23,115 @@ declare_id!("7UMWhVX2ZpqLa1iWqUM1tJz6LjRYWQ1oheZpuMtQKxs1");
 pub mod escrow {
     use super::*;
 
-    // Initialize a new one-way escrow payment and deposit funds in one transaction
-    pub fn initialize_escrow(
-        ctx: Context<InitializeEscrow>,
+    // Initialize a new one-way escrow payment and deposit funds in one transaction.
+    // Other programs may call this via CPI with one of their PDAs as `initiator`, signing
+    // with its seeds. For SOL deals the PDA must be system-owned (no data) and hold the
+    // deposit plus rent for the escrow, SOL vault and token vault, all of which it pays for.
+    // Every InitializeEscrow account is still required, including the token vault, mint and
+    // initiator token account for SOL deals; the optional ones may be passed as None.
+    // remaining_accounts: the mint's transfer-hook accounts, when it has one
+    pub fn initialize_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeEscrow<'info>>,
         escrow_id: u64,
         amount: u64,
         deal_type: DealType,
-        arbiter: Pubkey,
+        arbiter: Option<Pubkey>, // None for a two-party deal settled by the initiator or a schedule
         recipient: Pubkey,
+        fee_at_funding: bool, // Charge the platform fee now instead of on each release
+        expires_at: i64, // After this the initiator can claim the funds back (0 = never expires)
+        expiry_charges_fee: bool, // Charge the platform fee on the expiry refund too
+        referrer: Option<Pubkey>, // Affiliate paid a cut of the platform fee on each release
+        referrer_bps: u16, // Referrer's share of the platform fee, in basis points
+        release_condition: Option<ReleaseCondition>, // External account state release_funds waits for
     ) -> Result<()> {
+        let escrow_initiator = ctx.accounts.escrow_initiator();
+        let config = &ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+        config.validate_new_escrow(
+            &escrow_initiator,
+            arbiter,
+            expires_at,
+            &deal_type,
+            &ctx.accounts.forge_mint.key(),
+            now,
+        )?;
+        // A registered referral code resolves to its referrer; an explicit referrer must match it
+        let referrer = match ctx.accounts.referral_code.as_ref() {
+            Some(referral_code) => {
+                require!(
+                    referrer.is_none() || referrer == Some(referral_code.referrer),
+                    EscrowError::InvalidReferrer
+                );
+                Some(referral_code.referrer)
+            },
+            None => referrer,
+        };
+        require!(
+            referrer_bps <= 10_000 && (referrer.is_some() || referrer_bps == 0),
+            EscrowError::InvalidReferrer
+        );
+        // `init` adopts a PDA that already holds lamports, so make sure nothing was left
+        // behind or pre-funded; the vault must start out holding only its rent
+        require!(
+            Escrow::sol_vault_available(&ctx.accounts.escrow_sol_vault)? == 0,
+            EscrowError::VaultNotEmpty
+        );
+
+        // With the fee taken up front, only the principal goes into the vault
+        let (principal, half_fee) = if fee_at_funding {
+            let (_, half_fee) = Escrow::split_fee_bps(amount, config.capped_fee_bps(DEFAULT_FEE_BPS));
+            (amount - 2 * half_fee, half_fee)
+        } else {
+            (amount, 0)
+        };
+        let token_program = ctx.accounts.token_program.key();
+        require!(
+            token_program == token::ID || config.token_program_allowlist.contains(&token_program),
+            EscrowError::TokenProgramNotAllowed
+        );
+        ctx.accounts.validate_delegate(&deal_type, amount)?;
+        ctx.accounts.check_recipient_whitelist(&recipient)?;
+        ctx.accounts.record_deposit(&deal_type, principal, now)?;
+
         let escrow = &mut ctx.accounts.escrow;
         
         escrow.escrow_id = escrow_id;
-        escrow.initiator = ctx.accounts.initiator.key();
+        escrow.initiator = escrow_initiator;
         escrow.recipient = recipient;
-        escrow.arbiter = arbiter;
-        escrow.amount = amount;
+        escrow.arbiter = arbiter.unwrap_or_default();
+        escrow.amount = principal;
         escrow.released_amount = 0;
         escrow.deal_type = deal_type.clone();
         escrow.status = EscrowStatus::Initialized;
         escrow.bump = ctx.bumps.escrow;
+        escrow.sol_vault_bump = ctx.bumps.escrow_sol_vault;
+        escrow.fee_at_funding = fee_at_funding;
+        escrow.expires_at = expires_at;
+        escrow.expiry_charges_fee = expiry_charges_fee;
+        escrow.referrer = referrer;
+        escrow.referrer_bps = referrer_bps;
+        escrow.schema_version = ESCROW_SCHEMA_VERSION;
+        escrow.fees_paid = 2 * half_fee;
+        escrow.funded_at = now;
+        escrow.release_condition = release_condition;
+        escrow.token_program = token_program;
+        escrow.mint = match deal_type {
+            DealType::Sol => Pubkey::default(),
+            DealType::Forge => ctx.accounts.forge_mint.key(),
+        };
         
         // Deposit funds immediately after initialization
         match deal_type {
             DealType::Sol => {
                 // Transfer SOL to escrow vault
+                Escrow::assert_program(
+                    &ctx.accounts.system_program.to_account_info(),
+                    &anchor_lang::system_program::ID,
+                )?;
                 let transfer_instruction = system_instruction::transfer(
                     &ctx.accounts.initiator.key(),
                     &ctx.accounts.escrow_sol_vault.key(),
-                    amount,
+                    principal,
                 );
                 
                 invoke(
@@ -56,383 +145,5380 @@ pub mod escrow {
             },
             DealType::Forge => {
                 // Transfer FORGE tokens to escrow vault
-                let transfer_ctx = CpiContext::new(
+                let transfer_hook = escrow.transfer_hook(&ctx.accounts.forge_mint, ctx.remaining_accounts);
+                Escrow::vault_transfer(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.initiator_token_account.to_account_info(),
-                        to: ctx.accounts.escrow_token_vault.to_account_info(),
-                        authority: ctx.accounts.initiator.to_account_info(),
-                    },
-                );
-                token::transfer(transfer_ctx, amount)?;
+                    ctx.accounts.initiator_token_account.to_account_info(),
+                    ctx.accounts.escrow_token_vault.to_account_info(),
+                    ctx.accounts.initiator.to_account_info(),
+                    &[],
+                    principal,
+                    transfer_hook.as_ref(),
+                )?;
             }
         }
         
         // Set status to funded after successful deposit
         escrow.status = EscrowStatus::Funded;
+        ctx.accounts.config.lock_value(&deal_type, principal);
+        if let Some(user_index) = ctx.accounts.user_index.as_mut() {
+            user_index.register(escrow_id)?;
+        }
+
+        if fee_at_funding {
+            ctx.accounts.collect_funding_fee(half_fee)?;
+        }
         
         msg!("Escrow initialized and funded with ID: {} (Type: {:?}), Amount: {}", escrow_id, deal_type, amount);
         Ok(())
     }
 
-    // Release funds to recipient with 10% fee - now supports percentage
-    pub fn release_funds(
-        ctx: Context<ReleaseFunds>,
-        percentage: u8, // Percentage to release (1-100)
+    // Save reusable deal terms that escrows can later be created from until `valid_until`
+    pub fn create_template(
+        ctx: Context<CreateTemplate>,
+        template_id: u64,
+        deal_type: DealType,
+        arbiter: Option<Pubkey>,
+        recipient: Pubkey,
+        fee_at_funding: bool,
+        valid_until: i64, // Templates can't be used after this (0 = never expires)
     ) -> Result<()> {
-        let escrow_account_info = ctx.accounts.escrow.to_account_info();
-        let escrow = &mut ctx.accounts.escrow;
-        
-        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        let now = Clock::get()?.unix_timestamp;
+        require!(valid_until == 0 || valid_until > now, EscrowError::InvalidDeadline);
+
+        let template = &mut ctx.accounts.template;
+        template.creator = ctx.accounts.creator.key();
+        template.template_id = template_id;
+        template.deal_type = deal_type;
+        template.arbiter = arbiter;
+        template.recipient = recipient;
+        template.fee_at_funding = fee_at_funding;
+        template.valid_until = valid_until;
+        template.bump = ctx.bumps.template;
+
+        msg!("Template {} created by {}", template_id, template.creator);
+        Ok(())
+    }
+
+    // Initialize and fund an escrow on a template's terms
+    pub fn initialize_from_template<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeEscrow<'info>>,
+        escrow_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let template = ctx.accounts.template.as_ref().ok_or(EscrowError::MissingTemplate)?;
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            ctx.accounts.signer.key() == escrow.arbiter ||
-            ctx.accounts.signer.key() == escrow.initiator,
-            EscrowError::Unauthorized
+            template.valid_until == 0 || now <= template.valid_until,
+            EscrowError::TemplateExpired
         );
-        require!(percentage > 0 && percentage <= 100, EscrowError::InvalidPercentage);
 
-        // Calculate amounts based on percentage
-        let total_amount = escrow.amount;
-        let remaining_amount = total_amount - escrow.released_amount;
-        require!(remaining_amount > 0, EscrowError::NoFundsToRelease);
+        let deal_type = template.deal_type.clone();
+        let arbiter = template.arbiter;
+        let recipient = template.recipient;
+        let fee_at_funding = template.fee_at_funding;
+        initialize_escrow(
+            ctx,
+            escrow_id,
+            amount,
+            deal_type,
+            arbiter,
+            recipient,
+            fee_at_funding,
+            0,     // Never expires
+            false, // No expiry fee
+            None,  // No referrer
+            0,
+            None,  // Unconditional
+        )
+    }
 
-        let release_amount_before_fee = (remaining_amount * percentage as u64) / 100;
-        let fee_amount = release_amount_before_fee * 10 / 100; // 10% total fee
-        let half_fee = fee_amount / 2; // 5% each for different purposes
-        let net_release_amount = release_amount_before_fee - fee_amount;
+    // Release funds to recipient with 10% fee - now supports percentage.
+    // remaining_accounts: the auto-stake or recipient hook accounts when either is set,
+    // otherwise the mint's transfer-hook accounts when it has one
+    pub fn release_funds<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+        percentage: u8, // Percentage to release (1-100)
+        release_nonce: Option<u64>, // Client-chosen, strictly increasing; makes retries idempotent
+        override_recipient: Option<Pubkey>, // Arbiter only: pay this wallet instead of the recipient
+        fee_bps_override: Option<u16>, // Arbiter only: negotiated fee rate, up to the governance cap
+    ) -> Result<ReleaseResult> {
+        let escrow = &ctx.accounts.escrow;
+        
+        // Checked before the status so a drained escrow reports why, whatever its status says
+        require!(!escrow.is_fully_released(), EscrowError::NoFundsToRelease);
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        escrow.is_authorized_to_release(&ctx.accounts.signer.key())?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(escrow.usd_amount == 0, EscrowError::UsdEscrow);
+        require!(percentage > 0, EscrowError::PercentageIsZero);
+        require!(percentage <= 100, EscrowError::PercentageTooHigh);
+        if let Some(nonce) = release_nonce {
+            require!(nonce > escrow.last_release_nonce, EscrowError::DuplicateRelease);
+        }
+        ctx.accounts.check_payout_gate()?;
+        if let Some(override_recipient) = override_recipient {
+            ctx.accounts.validate_override_recipient(override_recipient)?;
+            msg!(
+                "Arbiter override: escrow ID {} paying {} instead of {}",
+                escrow.escrow_id,
+                override_recipient,
+                escrow.recipient
+            );
+        }
+        if let Some(fee_bps_override) = fee_bps_override {
+            require_keys_eq!(ctx.accounts.signer.key(), escrow.arbiter, EscrowError::Unauthorized);
+            require!(
+                fee_bps_override <= ctx.accounts.config.max_allowed_fee_bps,
+                EscrowError::FeeTooHigh
+            );
+            msg!("Arbiter fee override: escrow ID {} charged {} bps", escrow.escrow_id, fee_bps_override);
+        }
 
-        let deal_type = escrow.deal_type.clone();
-        let escrow_bump = escrow.bump;
-        let escrow_id = escrow.escrow_id;
+        ctx.accounts.ensure_vault_backs_remaining()?;
 
-        match deal_type {
-            DealType::Sol => {
-                // Handle SOL payment
-                Escrow::handle_sol_release(
-                    ctx.accounts.escrow_sol_vault.to_account_info(),
-                    ctx.accounts.recipient.to_account_info(),
-                    ctx.accounts.fee_wallet.to_account_info(),
-                    ctx.accounts.temp_fee_wallet.to_account_info(),
-                    net_release_amount,
-                    half_fee,
-                )?;
-            },
-            DealType::Forge => {
-                // Handle FORGE token payment
-                Escrow::handle_forge_release(
-                    ctx.accounts.escrow_token_vault.to_account_info(),
-                    ctx.accounts.recipient_token_account.to_account_info(),
-                    ctx.accounts.fee_wallet_token_account.to_account_info(),
-                    ctx.accounts.burn_token_account.to_account_info(),
-                    ctx.accounts.forge_mint.to_account_info(),
-                    ctx.accounts.token_program.to_account_info(),
-                    escrow_account_info,
-                    net_release_amount,
-                    half_fee,
-                    escrow_bump,
-                    escrow_id,
-                )?;
-            }
+        // Calculate amounts based on percentage
+        let release_amount_before_fee = escrow.percentage_of_remaining(percentage)?;
+        let release_amount_before_fee =
+            escrow.with_dust_swept(&ctx.accounts.config, release_amount_before_fee);
+        let fee_bps = match fee_bps_override {
+            Some(fee_bps_override) => fee_bps_override,
+            None => ctx.accounts.config.time_discounted_fee_bps(
+                ctx.accounts.initiator_fee_bps(),
+                escrow.funded_at,
+                Clock::get()?.unix_timestamp,
+            ),
+        };
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split_bps(release_amount_before_fee, fee_bps);
+
+        let paid_half_fee = ctx.accounts.transfer_release(
+            net_release_amount,
+            half_fee,
+            override_recipient,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.record_volume(release_amount_before_fee);
+        if let Some(hook) = ctx.accounts.escrow.recipient_hook {
+            ctx.accounts.call_recipient_hook(hook, ctx.remaining_accounts, net_release_amount)?;
         }
 
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, release_amount_before_fee);
+
         // Update released amount
+        let escrow = &mut ctx.accounts.escrow;
         escrow.released_amount += release_amount_before_fee;
+        escrow.fees_paid += 2 * half_fee;
+        if let Some(nonce) = release_nonce {
+            escrow.last_release_nonce = nonce;
+        }
         
         // Check if fully released
-        if escrow.released_amount >= escrow.amount {
-            escrow.status = EscrowStatus::Released;
-        }
+        escrow.status = if escrow.is_fully_released() {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
+        escrow.stamp_finalized()?;
         
         msg!(
             "Partial release ({}%) completed for escrow ID: {}. Released: {}/{}", 
             percentage, 
-            escrow_id,
+            escrow.escrow_id,
             escrow.released_amount,
             escrow.amount
         );
+        Ok(ReleaseResult {
+            gross: release_amount_before_fee,
+            net: net_release_amount,
+            fee: paid_half_fee,
+            burn: paid_half_fee,
+            new_status: escrow.status.clone(),
+        })
+    }
+
+    // Split the escrowed amount into milestones that are released or cancelled individually
+    pub fn set_milestones(ctx: Context<SetMilestones>, amounts: Vec<u64>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(
+            escrow.milestones.is_empty() && escrow.released_amount == 0,
+            EscrowError::InvalidMilestoneConfig
+        );
+        // cancel_milestone refunds only the initiator, so contributors' shares would be lost
+        require!(escrow.contributions.is_empty(), EscrowError::InvalidContributor);
+        // Milestone releases pay lamports and would bypass the USD accounting
+        require!(escrow.usd_amount == 0, EscrowError::UsdEscrow);
+        require!(
+            !amounts.is_empty() && amounts.len() <= MAX_MILESTONES,
+            EscrowError::InvalidMilestoneConfig
+        );
+        require!(amounts.iter().all(|amount| *amount > 0), EscrowError::InvalidMilestoneConfig);
+
+        let total = amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(EscrowError::InvalidMilestoneConfig)?;
+        require!(total == escrow.amount, EscrowError::InvalidMilestoneConfig);
+
+        escrow.milestones = amounts
+            .into_iter()
+            .map(|amount| Milestone {
+                amount,
+                status: MilestoneStatus::Pending,
+            })
+            .collect();
+
+        msg!("Escrow ID: {} split into {} milestones", escrow.escrow_id, escrow.milestones.len());
         Ok(())
     }
 
-    // New function: Get remaining releasable amount
-    pub fn get_remaining_amount(ctx: Context<GetRemainingAmount>) -> Result<u64> {
+    // Release a single milestone to the recipient with the usual 10% fee
+    pub fn release_milestone<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+        index: u8,
+    ) -> Result<()> {
         let escrow = &ctx.accounts.escrow;
-        let remaining = escrow.amount - escrow.released_amount;
-        msg!("Remaining amount for escrow ID {}: {}", escrow.escrow_id, remaining);
-        Ok(remaining)
-    }
 
-    // Cancel escrow and return funds to initiator
-    pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
-        let escrow_account_info = ctx.accounts.escrow.to_account_info();
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        escrow.is_authorized_to_release(&ctx.accounts.signer.key())?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        ctx.accounts.check_payout_gate()?;
+
+        let milestone = escrow
+            .milestones
+            .get(index as usize)
+            .ok_or(EscrowError::InvalidMilestone)?;
+        require!(milestone.status == MilestoneStatus::Pending, EscrowError::InvalidMilestoneStatus);
+
+        let release_amount_before_fee = milestone.amount;
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, release_amount_before_fee);
+
+        ctx.accounts.transfer_release(net_release_amount, half_fee, None, ctx.remaining_accounts)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, release_amount_before_fee);
+
         let escrow = &mut ctx.accounts.escrow;
-        
-        require!(
-            escrow.status == EscrowStatus::Funded, // Only funded escrows can be cancelled now
-            EscrowError::InvalidEscrowStatus
+        escrow.milestones[index as usize].status = MilestoneStatus::Released;
+        escrow.released_amount += release_amount_before_fee;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.settle_milestones()?;
+
+        msg!(
+            "Milestone {} released for escrow ID: {}. Released: {}/{}",
+            index,
+            escrow.escrow_id,
+            escrow.released_amount,
+            escrow.amount
         );
+        Ok(())
+    }
+
+    // Cancel a single un-released milestone and return its portion to the initiator.
+    // remaining_accounts: the mint's transfer-hook accounts, when it has one
+    pub fn cancel_milestone<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelEscrow<'info>>,
+        index: u8,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
         require!(
             ctx.accounts.signer.key() == escrow.arbiter ||
             ctx.accounts.signer.key() == escrow.initiator,
             EscrowError::Unauthorized
         );
 
-        let deal_type = escrow.deal_type.clone();
-        let remaining_amount = escrow.amount - escrow.released_amount; // Only return unreleased funds
-        let escrow_bump = escrow.bump;
-        let escrow_id = escrow.escrow_id;
+        let milestone = escrow
+            .milestones
+            .get(index as usize)
+            .ok_or(EscrowError::InvalidMilestone)?;
+        require!(milestone.status == MilestoneStatus::Pending, EscrowError::InvalidMilestoneStatus);
 
-        if remaining_amount > 0 {
-            match deal_type {
-                DealType::Sol => {
-                    // Return remaining SOL to initiator
-                    **ctx.accounts.escrow_sol_vault.to_account_info().try_borrow_mut_lamports()? -= remaining_amount;
-                    **ctx.accounts.initiator.to_account_info().try_borrow_mut_lamports()? += remaining_amount;
-                },
-                DealType::Forge => {
-                    // Return remaining FORGE tokens to initiator
-                    let escrow_id_bytes = escrow_id.to_le_bytes();
-                    let seeds = &[
-                        b"escrow",
-                        escrow_id_bytes.as_ref(),
-                        &[escrow_bump]
-                    ];
-                    let signer = &[&seeds[..]];
-                    
-                    let transfer_ctx = CpiContext::new_with_signer(
-                        ctx.accounts.token_program.to_account_info(),
-                        Transfer {
-                            from: ctx.accounts.escrow_token_vault.to_account_info(),
-                            to: ctx.accounts.initiator_token_account.to_account_info(),
-                            authority: escrow_account_info,
-                        },
-                        signer,
-                    );
-                    token::transfer(transfer_ctx, remaining_amount)?;
-                }
-            }
-        }
+        let refund_amount = milestone.amount;
+        let transfer_hook = ctx.accounts.transfer_hook(ctx.remaining_accounts);
+        ctx.accounts.refund_initiator(refund_amount, transfer_hook.as_ref())?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, refund_amount);
 
-        escrow.status = EscrowStatus::Cancelled;
-        msg!("Escrow cancelled for ID: {}", escrow.escrow_id);
+        // The cancelled portion is no longer owed, so it leaves the escrow total
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.milestones[index as usize].status = MilestoneStatus::Cancelled;
+        escrow.amount -= refund_amount;
+        escrow.settle_milestones()?;
+
+        msg!(
+            "Milestone {} cancelled for escrow ID: {}. Refunded: {}",
+            index,
+            escrow.escrow_id,
+            refund_amount
+        );
         Ok(())
     }
-}
 
-impl Escrow {
-    fn handle_sol_release(
-        escrow_sol_vault: AccountInfo,
-        recipient: AccountInfo,
-        fee_wallet: AccountInfo,
-        temp_fee_wallet: AccountInfo,
-        release_amount: u64,
-        half_fee: u64,
-    ) -> Result<()> {
-        // Send release amount to recipient
-        **escrow_sol_vault.try_borrow_mut_lamports()? -= release_amount;
-        **recipient.try_borrow_mut_lamports()? += release_amount;
-        
-        // Transfer 5% fee to fee wallet
-        **escrow_sol_vault.try_borrow_mut_lamports()? -= half_fee;
-        **fee_wallet.try_borrow_mut_lamports()? += half_fee;
-        
-        // TODO: Buy Forge token from dex by 5% of fee and burn it
-        // Note: In production, implement DEX swap for remaining 5%
-        // For now, sending remaining fee to temp fee wallet
-        **escrow_sol_vault.try_borrow_mut_lamports()? -= half_fee;
-        **temp_fee_wallet.try_borrow_mut_lamports()? += half_fee;
-        
+    // Either party disputes one milestone; the others keep releasing normally
+    pub fn dispute_milestone(ctx: Context<DisputeMilestone>, index: u8) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let party = ctx.accounts.party.key();
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(
+            party == escrow.initiator || party == escrow.recipient,
+            EscrowError::Unauthorized
+        );
+
+        let milestone = escrow
+            .milestones
+            .get_mut(index as usize)
+            .ok_or(EscrowError::InvalidMilestone)?;
+        require!(milestone.status == MilestoneStatus::Pending, EscrowError::InvalidMilestoneStatus);
+
+        milestone.status = MilestoneStatus::Disputed;
+
+        msg!("Milestone {} of escrow ID: {} disputed by {}", index, escrow.escrow_id, party);
         Ok(())
     }
-    
-    fn handle_forge_release<'info>(
-        escrow_token_vault: AccountInfo<'info>,
-        recipient_token_account: AccountInfo<'info>,
-        fee_wallet_token_account: AccountInfo<'info>,
-        burn_token_account: AccountInfo<'info>, 
-        forge_mint: AccountInfo<'info>,
-        token_program: AccountInfo<'info>,
-        escrow_authority: AccountInfo<'info>,
-        release_amount: u64,
-        half_fee: u64,
-        bump: u8,
-        escrow_id: u64,
+
+    // Initiator or recipient freezes the whole escrow until the arbiter settles it
+    pub fn dispute_escrow(ctx: Context<DisputeMilestone>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let party = ctx.accounts.party.key();
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.has_arbiter(), EscrowError::NoArbiter);
+        require!(
+            party == escrow.initiator || party == escrow.recipient,
+            EscrowError::Unauthorized
+        );
+
+        escrow.status = EscrowStatus::Disputed;
+
+        msg!("Escrow ID: {} disputed by {}", escrow.escrow_id, party);
+        Ok(())
+    }
+
+    // Arbiter splits a disputed milestone: the recipient's share is paid (fee applies to it)
+    // and the rest is refunded to the initiator
+    pub fn resolve_milestone_dispute(
+        ctx: Context<Settle>,
+        index: u8,
+        recipient_bps: u16,
     ) -> Result<()> {
-        let escrow_id_bytes = escrow_id.to_le_bytes();
-        let seeds = &[
-            b"escrow",
-            escrow_id_bytes.as_ref(),
-            &[bump]
-        ];
-        let signer = &[&seeds[..]];
-        
-        // Send release amount to recipient
-        let transfer_ctx = CpiContext::new_with_signer(
-            token_program.clone(),
-            Transfer {
-                from: escrow_token_vault.clone(),
-                to: recipient_token_account,
-                authority: escrow_authority.clone(),
-            },
-            signer,
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        // A pause holds the parties' own releases and cancels, not the arbiter's resolution
+        require!(ctx.accounts.signer.key() == escrow.arbiter, EscrowError::Unauthorized);
+        escrow.check_require_both(&ctx.accounts.signer.key())?;
+        require!(recipient_bps <= 10_000, EscrowError::InvalidPercentage);
+
+        let milestone = escrow
+            .milestones
+            .get(index as usize)
+            .ok_or(EscrowError::InvalidMilestone)?;
+        require!(milestone.status == MilestoneStatus::Disputed, EscrowError::InvalidMilestoneStatus);
+
+        let recipient_amount = (milestone.amount as u128 * recipient_bps as u128 / 10_000) as u64;
+        let refund_amount = milestone.amount - recipient_amount;
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, recipient_amount);
+
+        ctx.accounts.transfer_settlement(net_release_amount, half_fee, refund_amount)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, recipient_amount + refund_amount);
+
+        // As with a cancelled milestone, the refunded part leaves the escrow total
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.milestones[index as usize].status = if recipient_amount > 0 {
+            MilestoneStatus::Released
+        } else {
+            MilestoneStatus::Cancelled
+        };
+        escrow.released_amount += recipient_amount;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.amount -= refund_amount;
+        escrow.settle_milestones()?;
+
+        msg!(
+            "Milestone {} dispute resolved for escrow ID: {}. Recipient: {} ({} bps), Refunded: {}",
+            index,
+            escrow.escrow_id,
+            recipient_amount,
+            recipient_bps,
+            refund_amount
         );
-        token::transfer(transfer_ctx, release_amount)?;
-        
-        // Transfer 5% fee to fee wallet
-        let transfer_ctx = CpiContext::new_with_signer(
-            token_program.clone(),
-            Transfer {
-                from: escrow_token_vault.clone(),
-                to: fee_wallet_token_account,
-                authority: escrow_authority.clone(),
-            },
-            signer,
+        Ok(())
+    }
+
+    // Require the recipient to accept the deal before the deadline; releases wait for acceptance
+    pub fn set_acceptance_deadline(
+        ctx: Context<SetAcceptanceDeadline>,
+        accept_deadline: i64,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(
+            escrow.released_amount == 0 && !escrow.accepted,
+            EscrowError::InvalidEscrowStatus
         );
-        token::transfer(transfer_ctx, half_fee)?;
-        
-        // Burn 5% of tokens
-        let transfer_ctx = CpiContext::new_with_signer(
-            token_program.clone(),
-            Transfer {
-                from: escrow_token_vault.clone(),
-                to: burn_token_account,
-                authority: escrow_authority.clone(),
-            },
-            signer,
+        require!(accept_deadline > now, EscrowError::InvalidDeadline);
+
+        escrow.accept_deadline = accept_deadline;
+
+        msg!("Escrow ID: {} must be accepted by {}", escrow.escrow_id, accept_deadline);
+        Ok(())
+    }
+
+    // Treat the deal as accepted if the recipient stays silent for this many seconds (0 = off)
+    pub fn set_auto_accept_window(
+        ctx: Context<SetAcceptanceDeadline>,
+        auto_accept_window: i64,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(!escrow.accepted, EscrowError::InvalidEscrowStatus);
+        require!(auto_accept_window >= 0, EscrowError::InvalidDeadline);
+
+        escrow.auto_accept_at = if auto_accept_window == 0 { 0 } else { now + auto_accept_window };
+
+        msg!("Escrow ID: {} auto-accepts at {}", escrow.escrow_id, escrow.auto_accept_at);
+        Ok(())
+    }
+
+    // Recipient accepts the deal, unlocking releases
+    pub fn accept_escrow(ctx: Context<AcceptEscrow>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.recipient.key() == escrow.recipient, EscrowError::Unauthorized);
+        require!(
+            escrow.accept_deadline == 0 || now <= escrow.accept_deadline,
+            EscrowError::AcceptanceWindowClosed
         );
-        token::transfer(transfer_ctx, half_fee)?;
-        
+
+        escrow.accepted = true;
+
+        msg!("Escrow ID: {} accepted by recipient", escrow.escrow_id);
         Ok(())
     }
-}
 
-// Account Contexts
-#[derive(Accounts)]
-#[instruction(escrow_id: u64)]
-pub struct InitializeEscrow<'info> {
-    #[account(
+    // Initiator takes the funds back when the recipient never accepted in time
+    pub fn reclaim_unaccepted<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelEscrow<'info>>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(ctx.accounts.signer.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.awaiting_acceptance()?, EscrowError::InvalidEscrowStatus);
+        require!(now > escrow.accept_deadline, EscrowError::AcceptanceWindowOpen);
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        if remaining_amount > 0 {
+            ctx.accounts.refund_remaining(remaining_amount, ctx.remaining_accounts)?;
+        }
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, remaining_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.status = EscrowStatus::Cancelled;
+        escrow.stamp_finalized()?;
+
+        msg!("Unaccepted escrow ID: {} reclaimed by initiator", escrow.escrow_id);
+        Ok(())
+    }
+
+    // Initiator takes back what is left once the escrow expires, minus the platform fee
+    // if the escrow was created with expiry_charges_fee. Recipient accounts must be the initiator's.
+    pub fn claim_expired<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(ctx.accounts.signer.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.expires_at != 0, EscrowError::NoExpiry);
+        require!(now >= escrow.expires_at, EscrowError::NotExpired);
+        // Contributors are refunded proportionally through cancel_escrow instead
+        require!(escrow.contributions.is_empty(), EscrowError::InvalidContributor);
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        require!(remaining_amount > 0, EscrowError::NoFundsToRelease);
+        let (net_refund_amount, half_fee) = if escrow.expiry_charges_fee {
+            escrow.release_fee_split(&ctx.accounts.config, remaining_amount)
+        } else {
+            (remaining_amount, 0)
+        };
+
+        ctx.accounts.transfer_expiry_refund(net_refund_amount, half_fee)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, remaining_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.released_amount += remaining_amount;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.status = EscrowStatus::Cancelled;
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "Expired escrow ID: {} claimed by initiator. Refunded: {}",
+            escrow.escrow_id,
+            net_refund_amount
+        );
+        Ok(())
+    }
+
+    // Either party freezes releases and cancels while the deal is renegotiated
+    pub fn pause_escrow(ctx: Context<PauseEscrow>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let party = ctx.accounts.party.key();
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(
+            party == escrow.initiator || party == escrow.recipient,
+            EscrowError::Unauthorized
+        );
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+
+        escrow.paused_by = Some(party);
+
+        msg!("Escrow ID: {} paused by {}", escrow.escrow_id, party);
+        Ok(())
+    }
+
+    // Only the party that paused the escrow can resume it
+    pub fn unpause_escrow(ctx: Context<PauseEscrow>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let party = ctx.accounts.party.key();
+
+        require!(escrow.paused_by.is_some(), EscrowError::NotPaused);
+        require!(escrow.paused_by == Some(party), EscrowError::Unauthorized);
+
+        escrow.paused_by = None;
+
+        msg!("Escrow ID: {} unpaused by {}", escrow.escrow_id, party);
+        Ok(())
+    }
+
+    // Initiator schedules the remaining funds to be paid out at a future time
+    pub fn schedule_release(ctx: Context<ScheduleRelease>, release_at: i64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(release_at > now, EscrowError::InvalidDeadline);
+
+        escrow.release_at = release_at;
+
+        msg!("Escrow ID: {} scheduled for release at {}", escrow.escrow_id, release_at);
+        Ok(())
+    }
+
+    // Initiator locks a high-value escrow so every payout needs both the scheduled time to
+    // have passed and the arbiter to sign; a keeper can no longer run the scheduled payout.
+    // One-way, so it cannot be switched off to slip a release through on either gate alone.
+    pub fn enable_require_both(ctx: Context<ScheduleRelease>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.has_arbiter(), EscrowError::ArbiterApprovalRequired);
+        require!(escrow.release_at != 0, EscrowError::ReleaseNotScheduled);
+
+        escrow.require_both = true;
+
+        msg!("Escrow ID: {} now requires both its release time and arbiter approval", escrow.escrow_id);
+        Ok(())
+    }
+
+    // Permissionless: anyone (e.g. a keeper) pays out the remaining funds once the scheduled time passes
+    pub fn execute_scheduled_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(escrow.release_at != 0, EscrowError::ReleaseNotScheduled);
+        require!(now >= escrow.release_at, EscrowError::ReleaseNotDue);
+        ctx.accounts.check_payout_gate()?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(escrow.usd_amount == 0, EscrowError::UsdEscrow);
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        require!(remaining_amount > 0, EscrowError::NoFundsToRelease);
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, remaining_amount);
+
+        ctx.accounts.transfer_release(net_release_amount, half_fee, None, ctx.remaining_accounts)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, remaining_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.released_amount += remaining_amount;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.status = EscrowStatus::Released;
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "Scheduled release executed for escrow ID: {}. Released: {}",
+            escrow.escrow_id,
+            remaining_amount
+        );
+        Ok(())
+    }
+
+    // Recipient asks the arbiter to release a percentage of the remaining funds
+    pub fn request_release(ctx: Context<AcceptEscrow>, percentage: u8) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.recipient.key() == escrow.recipient, EscrowError::Unauthorized);
+        require!(escrow.has_arbiter(), EscrowError::NoArbiter);
+        require!(percentage > 0, EscrowError::PercentageIsZero);
+        require!(percentage <= 100, EscrowError::PercentageTooHigh);
+
+        // A newer request replaces any that is still pending
+        escrow.pending_release = percentage;
+
+        msg!("Escrow ID: {} release of {}% requested", escrow.escrow_id, percentage);
+        Ok(())
+    }
+
+    // Arbiter approves the recipient's pending request, releasing exactly what was asked
+    pub fn approve_pending_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+    ) -> Result<ReleaseResult> {
+        let escrow = &mut ctx.accounts.escrow;
+        let percentage = escrow.pending_release;
+
+        require!(percentage > 0, EscrowError::NoPendingRelease);
+        require!(ctx.accounts.signer.key() == escrow.arbiter, EscrowError::Unauthorized);
+
+        escrow.pending_release = 0;
+
+        release_funds(ctx, percentage, None, None, None)
+    }
+
+    // Arbiter turns down the recipient's pending request
+    pub fn reject_pending_release(ctx: Context<ReviewRelease>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.pending_release > 0, EscrowError::NoPendingRelease);
+        require!(ctx.accounts.arbiter.key() == escrow.arbiter, EscrowError::Unauthorized);
+
+        escrow.pending_release = 0;
+
+        msg!("Escrow ID: {} release request rejected", escrow.escrow_id);
+        Ok(())
+    }
+
+    // Lock a USD value (6 decimals) for a SOL deal; releases then pay its SOL equivalent
+    pub fn set_usd_amount(ctx: Context<SetUsdAmount>, usd_amount: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.deal_type == DealType::Sol, EscrowError::InvalidDealType);
+        require!(escrow.released_amount == 0, EscrowError::InvalidEscrowStatus);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(usd_amount > 0, EscrowError::InvalidUsdAmount);
+
+        escrow.usd_amount = usd_amount;
+
+        msg!("Escrow ID: {} denominated in USD: {}", escrow.escrow_id, usd_amount);
+        Ok(())
+    }
+
+    // Release a percentage of the remaining USD value, paid in SOL at the current oracle price.
+    // remaining_accounts: [SOL/USD price feed]
+    pub fn release_usd_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+        percentage: u8, // Percentage of the remaining USD value to release (1-100)
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        escrow.is_authorized_to_release(&ctx.accounts.signer.key())?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        require!(escrow.usd_amount > 0, EscrowError::NotUsdEscrow);
+        ctx.accounts.check_payout_gate()?;
+        require!(percentage > 0, EscrowError::PercentageIsZero);
+        require!(percentage <= 100, EscrowError::PercentageTooHigh);
+
+        let price_feed = ctx.remaining_accounts.first().ok_or(EscrowError::InvalidPriceFeed)?;
+        require_keys_eq!(price_feed.key(), ctx.accounts.config.price_feed, EscrowError::InvalidPriceFeed);
+        let price = Escrow::load_usd_price(price_feed)?;
+
+        let usd_remaining = escrow
+            .usd_amount
+            .checked_sub(escrow.usd_released_amount)
+            .ok_or(EscrowError::NoFundsToRelease)?;
+        let usd_release = usd_remaining as u128 * percentage as u128 / 100;
+        let usd_release = u64::try_from(usd_release).map_err(|_| EscrowError::InvalidUsdAmount)?;
+        let implied_lamports = Escrow::usd_to_base_units(usd_release, &price, SOL_DECIMALS)?;
+
+        // The vault only holds what was deposited; if SOL fell, pay out what is left
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        let release_amount_before_fee = implied_lamports.min(remaining_amount);
+        require!(release_amount_before_fee > 0, EscrowError::NoFundsToRelease);
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, release_amount_before_fee);
+
+        ctx.accounts.transfer_release(net_release_amount, half_fee, None, &[])?;
+        ctx.accounts.config.unlock_value(&DealType::Sol, release_amount_before_fee);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.released_amount += release_amount_before_fee;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.usd_released_amount += usd_release;
+        escrow.status = if escrow.usd_released_amount >= escrow.usd_amount ||
+            escrow.released_amount >= escrow.amount
+        {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "USD release ({}%) completed for escrow ID: {}. USD: {}, lamports: {}",
+            percentage,
+            escrow.escrow_id,
+            usd_release,
+            release_amount_before_fee
+        );
+        Ok(())
+    }
+
+    // Release a fixed USD amount (6 decimals) of a FORGE escrow, converted to tokens at the
+    // current oracle price and capped at what is left.
+    // remaining_accounts: [FORGE/USD price feed]
+    pub fn release_usd<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseFunds<'info>>,
+        usd_amount: u64,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        escrow.is_authorized_to_release(&ctx.accounts.signer.key())?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        require!(escrow.deal_type == DealType::Forge, EscrowError::InvalidDealType);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        ctx.accounts.check_payout_gate()?;
+        require!(usd_amount > 0, EscrowError::InvalidUsdAmount);
+
+        let price_feed = ctx.remaining_accounts.first().ok_or(EscrowError::InvalidPriceFeed)?;
+        require_keys_eq!(
+            price_feed.key(),
+            ctx.accounts.config.token_price_feed,
+            EscrowError::InvalidPriceFeed
+        );
+        let price = Escrow::load_usd_price(price_feed)?;
+        let decimals = Escrow::deal_account(&ctx.accounts.forge_mint)?.decimals;
+        let implied_amount = Escrow::usd_to_base_units(usd_amount, &price, decimals)?;
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        let release_amount_before_fee = implied_amount.min(remaining_amount);
+        require!(release_amount_before_fee > 0, EscrowError::NoFundsToRelease);
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, release_amount_before_fee);
+
+        ctx.accounts.transfer_release(net_release_amount, half_fee, None, &[])?;
+        ctx.accounts.config.unlock_value(&DealType::Forge, release_amount_before_fee);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.released_amount += release_amount_before_fee;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.status = if escrow.is_fully_released() {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "USD release of {} completed for escrow ID: {}. Tokens: {}",
+            usd_amount,
+            escrow.escrow_id,
+            release_amount_before_fee
+        );
+        Ok(())
+    }
+
+    // Attach a label, growing the escrow account to fit it. The initiator pays the extra rent.
+    pub fn add_label(ctx: Context<AddLabel>, label: String) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(label.len() <= MAX_LABEL_LEN, EscrowError::LabelTooLong);
+
+        escrow.label = label;
+
+        msg!("Escrow ID: {} labelled: {}", escrow.escrow_id, escrow.label);
+        Ok(())
+    }
+
+    // Initiator corrects the label and terms before anything is released, with the recipient
+    // co-signing. A hash of the replaced metadata is kept so earlier terms stay auditable.
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        label: String,
+        terms_uri_hash: [u8; 32],
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.released_amount == 0, EscrowError::MetadataLocked);
+        require!(label.len() <= MAX_LABEL_LEN, EscrowError::LabelTooLong);
+
+        let previous = escrow.metadata_hash();
+        if escrow.metadata_history.len() == MAX_METADATA_HISTORY {
+            escrow.metadata_history.remove(0);
+        }
+        escrow.metadata_history.push(previous);
+        escrow.label = label;
+        escrow.terms_uri_hash = terms_uri_hash;
+
+        msg!(
+            "Escrow ID: {} metadata updated ({} prior versions kept)",
+            escrow.escrow_id,
+            escrow.metadata_history.len()
+        );
+        Ok(())
+    }
+
+    // Register the wallets (and their basis-point shares) that split releases pay out to
+    pub fn set_split_recipients(
+        ctx: Context<SetSplitRecipients>,
+        split_recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(ctx.accounts.initiator.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.released_amount == 0, EscrowError::InvalidSplitConfig);
+        require!(
+            split_recipients.len() <= MAX_SPLIT_RECIPIENTS,
+            EscrowError::TooManyRecipients
+        );
+        require!(!split_recipients.is_empty(), EscrowError::InvalidSplitConfig);
+
+        let total_bps: u32 = split_recipients.iter().map(|r| r.bps as u32).sum();
+        require!(total_bps == 10_000, EscrowError::InvalidSplitConfig);
+
+        escrow.split_recipients = split_recipients;
+
+        msg!(
+            "Escrow ID: {} will split releases across {} recipients",
+            escrow.escrow_id,
+            escrow.split_recipients.len()
+        );
+        Ok(())
+    }
+
+    // Release a percentage of the remaining funds across the registered split recipients.
+    // remaining_accounts: one payee per split recipient, in order (wallet for SOL, token account for FORGE)
+    pub fn release_split<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseFunds<'info>>,
+        percentage: u8,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        escrow.is_authorized_to_release(&ctx.accounts.signer.key())?;
+        require!(!escrow.awaiting_acceptance()?, EscrowError::NotAccepted);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(escrow.usd_amount == 0, EscrowError::UsdEscrow);
+        ctx.accounts.check_payout_gate()?;
+        require!(percentage > 0, EscrowError::PercentageIsZero);
+        require!(percentage <= 100, EscrowError::PercentageTooHigh);
+        require!(!escrow.split_recipients.is_empty(), EscrowError::InvalidSplitConfig);
+        require!(
+            ctx.remaining_accounts.len() <= MAX_SPLIT_RECIPIENTS,
+            EscrowError::TooManyRecipients
+        );
+        require!(
+            ctx.remaining_accounts.len() == escrow.split_recipients.len(),
+            EscrowError::InvalidSplitConfig
+        );
+
+        let release_amount_before_fee = escrow.percentage_of_remaining(percentage)?;
+        let (net_release_amount, half_fee) =
+            escrow.release_fee_split(&ctx.accounts.config, release_amount_before_fee);
+
+        ctx.accounts.transfer_split(net_release_amount, half_fee, ctx.remaining_accounts)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, release_amount_before_fee);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.released_amount += release_amount_before_fee;
+        escrow.fees_paid += 2 * half_fee;
+        escrow.status = if escrow.released_amount >= escrow.amount {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::PartiallyReleased
+        };
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "Split release ({}%) completed for escrow ID: {}. Released: {}/{}",
+            percentage,
+            escrow.escrow_id,
+            escrow.released_amount,
+            escrow.amount
+        );
+        Ok(())
+    }
+
+    // Recipient opts in (or out) of having FORGE releases deposited into an allowlisted
+    // staking program
+    pub fn set_auto_stake(
+        ctx: Context<SetAutoStake>,
+        staking_program: Option<Pubkey>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(ctx.accounts.recipient.key() == escrow.recipient, EscrowError::Unauthorized);
+        require!(
+            staking_program.is_none() || escrow.deal_type == DealType::Forge,
+            EscrowError::InvalidDealType
+        );
+        // The staking program is called with the escrow PDA signing over its vault
+        if let Some(program) = staking_program {
+            require!(
+                ctx.accounts.config.staking_allowlist.contains(&program),
+                EscrowError::InvalidStakingProgram
+            );
+        }
+        // Both consume remaining_accounts on release
+        require!(
+            staking_program.is_none() || escrow.recipient_hook.is_none(),
+            EscrowError::InvalidRecipientHook
+        );
+
+        escrow.auto_stake_program = staking_program;
+
+        msg!("Auto-stake for escrow ID: {} set to {:?}", escrow.escrow_id, staking_program);
+        Ok(())
+    }
+
+    // Recipient registers an allowlisted program that release_funds calls after paying out.
+    // A recipient that is a program PDA, such as a payment splitter, signs via CPI and can
+    // use the hook to distribute what it was paid.
+    pub fn set_recipient_hook(
+        ctx: Context<SetAutoStake>,
+        recipient_hook: Option<Pubkey>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(ctx.accounts.recipient.key() == escrow.recipient, EscrowError::Unauthorized);
+        require!(
+            recipient_hook.is_none() || escrow.auto_stake_program.is_none(),
+            EscrowError::InvalidRecipientHook
+        );
+
+        escrow.recipient_hook = recipient_hook;
+
+        msg!("Recipient hook for escrow ID: {} set to {:?}", escrow.escrow_id, recipient_hook);
+        Ok(())
+    }
+
+    // Recipient assigns the right to whatever is still unreleased to another wallet, e.g. when
+    // selling the receivable. Not while the escrow or any milestone is disputed, and only to a
+    // wallet the initiator's recipient whitelist allows.
+    pub fn assign_payout(ctx: Context<AssignPayout>, new_recipient: Pubkey) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(ctx.accounts.recipient.key() == escrow.recipient, EscrowError::Unauthorized);
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(
+            !escrow.milestones.iter().any(|m| m.status == MilestoneStatus::Disputed),
+            EscrowError::InvalidEscrowStatus
+        );
+        require!(
+            new_recipient != Pubkey::default() && new_recipient != escrow.recipient,
+            EscrowError::InvalidAssignee
+        );
+        RecipientWhitelist::check(&ctx.accounts.recipient_whitelist, &new_recipient)?;
+
+        let previous = escrow.recipient;
+        escrow.recipient = new_recipient;
+        // Payout routing was the previous recipient's choice
+        escrow.recipient_hook = None;
+        escrow.auto_stake_program = None;
+
+        msg!(
+            "Escrow ID: {} payout assigned from {} to {}",
+            escrow.escrow_id,
+            previous,
+            new_recipient
+        );
+        Ok(())
+    }
+
+    // Let any wallet top up a funded escrow; contributions are refunded proportionally on cancel
+    pub fn contribute(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(amount > 0, EscrowError::InsufficientFunds);
+
+        let contributor = ctx.accounts.contributor.key();
+        let existing = escrow.contributions.iter().position(|c| c.contributor == contributor);
+        require!(
+            existing.is_some() || escrow.contributions.len() < MAX_CONTRIBUTORS,
+            EscrowError::TooManyContributors
+        );
+
+        match escrow.deal_type {
+            DealType::Sol => {
+                require_keys_eq!(
+                    ctx.accounts.escrow_sol_vault.key(),
+                    escrow.sol_vault_address()?,
+                    EscrowError::InvalidDealType
+                );
+                let transfer_instruction = system_instruction::transfer(
+                    &contributor,
+                    &ctx.accounts.escrow_sol_vault.key(),
+                    amount,
+                );
+                invoke(
+                    &transfer_instruction,
+                    &[
+                        ctx.accounts.contributor.to_account_info(),
+                        ctx.accounts.escrow_sol_vault.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                )?;
+            },
+            DealType::Forge => {
+                let transfer_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.contributor_token_account.to_account_info(),
+                        to: ctx.accounts.escrow_token_vault.to_account_info(),
+                        authority: ctx.accounts.contributor.to_account_info(),
+                    },
+                );
+                Escrow::token_transfer(transfer_ctx, amount)?;
+            }
+        }
+
+        let deal_type = ctx.accounts.escrow.deal_type.clone();
+        UserStats::record_deposit(
+            ctx.accounts.user_stats.as_deref_mut(),
+            &ctx.accounts.config,
+            &deal_type,
+            amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        ctx.accounts.config.lock_value(&deal_type, amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        match existing {
+            Some(index) => escrow.contributions[index].amount += amount,
+            None => escrow.contributions.push(Contribution { contributor, amount }),
+        }
+        escrow.amount += amount;
+
+        msg!(
+            "Contribution of {} by {} to escrow ID: {}. Total: {}",
+            amount,
+            contributor,
+            escrow.escrow_id,
+            escrow.amount
+        );
+        Ok(())
+    }
+
+    // New function: Get remaining releasable amount
+    pub fn get_remaining_amount(ctx: Context<GetRemainingAmount>) -> Result<u64> {
+        let escrow = &ctx.accounts.escrow;
+        let remaining = escrow.amount - escrow.released_amount;
+        msg!("Remaining amount for escrow ID {}: {}", escrow.escrow_id, remaining);
+        Ok(remaining)
+    }
+
+    // Emit a tamper-evident commitment to the escrow's full current state for off-chain
+    // archiving; recomputing the hash from the account data at that slot verifies it
+    pub fn snapshot_escrow(ctx: Context<GetRemainingAmount>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let clock = Clock::get()?;
+
+        emit!(StateSnapshot {
+            version: EVENT_SCHEMA_VERSION,
+            escrow: escrow.key(),
+            escrow_id: escrow.escrow_id,
+            state_hash: escrow.state_hash()?,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Remaining amount the recipient would actually receive after the platform fee
+    pub fn get_remaining_net(ctx: Context<GetRemainingNet>) -> Result<u64> {
+        let escrow = &ctx.accounts.escrow;
+        let remaining = escrow.amount - escrow.released_amount;
+        let (net_remaining, _) = escrow.release_fee_split(&ctx.accounts.config, remaining);
+        msg!("Remaining net amount for escrow ID {}: {}", escrow.escrow_id, net_remaining);
+        Ok(net_remaining)
+    }
+
+    // True once everything deposited has been paid out
+    pub fn is_fully_released(ctx: Context<GetRemainingAmount>) -> Result<bool> {
+        Ok(ctx.accounts.escrow.is_fully_released())
+    }
+
+    // Whether this key may sign release_funds for the escrow, so clients can check up front
+    pub fn can_release(ctx: Context<GetRemainingAmount>, signer: Pubkey) -> Result<bool> {
+        Ok(ctx.accounts.escrow.is_authorized_to_release(&signer).is_ok())
+    }
+
+    // Core escrow state, including the rules version it was created under
+    pub fn get_escrow_details(ctx: Context<GetRemainingAmount>) -> Result<EscrowDetails> {
+        Ok(ctx.accounts.escrow.details())
+    }
+
+    // Details of every escrow passed in remaining_accounts, in order, so dashboards need
+    // one call instead of one per escrow
+    pub fn get_escrow_details_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetConfig<'info>>,
+    ) -> Result<Vec<EscrowDetails>> {
+        require!(
+            ctx.remaining_accounts.len() <= MAX_ESCROW_DETAILS_BATCH,
+            EscrowError::PageTooLarge
+        );
+
+        ctx.remaining_accounts
+            .iter()
+            .map(|info| Ok(Account::<Escrow>::try_from(info)?.details()))
+            .collect()
+    }
+
+    // Up to `count` of a user's escrow IDs starting at `start`, small enough for return data
+    pub fn get_user_escrows(ctx: Context<GetUserEscrows>, start: u32, count: u8) -> Result<Vec<u64>> {
+        require!(count <= MAX_USER_ESCROWS_PAGE, EscrowError::PageTooLarge);
+        let escrow_ids = &ctx.accounts.user_index.escrow_ids;
+        let start = (start as usize).min(escrow_ids.len());
+        let end = (start + count as usize).min(escrow_ids.len());
+        Ok(escrow_ids[start..end].to_vec())
+    }
+
+    // How many escrows the user has created through their index, i.e. their next per-user
+    // sequence number. Escrow PDAs are seeded by the global escrow_id alone, so this is not
+    // an available escrow ID; clients still have to pick one no other user has taken.
+    pub fn get_next_escrow_id(ctx: Context<GetUserEscrows>) -> Result<u64> {
+        Ok(ctx.accounts.user_index.next_id)
+    }
+
+    // Whether token escrows may be created in this mint, so frontends can rule it out up front
+    pub fn is_mint_allowed(ctx: Context<GetConfig>, mint: Pubkey) -> Result<bool> {
+        Ok(ctx.accounts.config.is_mint_allowed(&mint))
+    }
+
+    // Program-wide settings and the value currently locked across all escrows
+    pub fn get_config(ctx: Context<GetConfig>) -> Result<ConfigDetails> {
+        let config = &ctx.accounts.config;
+
+        Ok(ConfigDetails {
+            admin: config.admin,
+            treasury: config.treasury,
+            permissioned: config.permissioned,
+            total_value_locked_sol: config.total_value_locked_sol,
+            total_value_locked_token: config.total_value_locked_token,
+        })
+    }
+
+    // Bumps of an escrow's PDAs, so clients building CPIs don't each re-derive them
+    pub fn get_bumps(ctx: Context<GetBumps>, escrow_id: u64) -> Result<EscrowBumps> {
+        let escrow_id_bytes = escrow_id.to_le_bytes();
+        let (escrow, escrow_bump) =
+            Pubkey::find_program_address(&[b"escrow", escrow_id_bytes.as_ref()], ctx.program_id);
+        let (_, sol_vault_bump) =
+            Pubkey::find_program_address(&[b"sol_vault", escrow_id_bytes.as_ref()], ctx.program_id);
+        // The token vault is the escrow's associated token account under the mint's own program
+        let mint = ctx.accounts.forge_mint.to_account_info();
+        let (_, token_vault_bump) = Pubkey::find_program_address(
+            &[escrow.as_ref(), mint.owner.as_ref(), mint.key.as_ref()],
+            &anchor_spl::associated_token::ID,
+        );
+
+        Ok(EscrowBumps {
+            escrow: escrow_bump,
+            sol_vault: sol_vault_bump,
+            token_vault: token_vault_bump,
+        })
+    }
+
+    // Everything a frontend needs to render the escrow in one call
+    pub fn get_display_info(ctx: Context<GetDisplayInfo>) -> Result<DisplayInfo> {
+        let escrow = &ctx.accounts.escrow;
+
+        let (decimals, label) = match escrow.deal_type {
+            DealType::Sol => (SOL_DECIMALS, "SOL"),
+            DealType::Forge => {
+                let mint = ctx.accounts.mint.as_ref().ok_or(EscrowError::InvalidDealType)?;
+                (mint.decimals, "FORGE")
+            },
+        };
+
+        Ok(DisplayInfo {
+            deal_type: escrow.deal_type.clone(),
+            amount: escrow.amount,
+            decimals,
+            label: label.to_string(),
+        })
+    }
+
+    // Cancel escrow and return funds to initiator. Within the cooling-off window after
+    // funding the initiator may cancel for a full refund even if paused, but never while disputed.
+    pub fn cancel_escrow<'info>(ctx: Context<'_, '_, 'info, 'info, CancelEscrow<'info>>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let cooling_off = ctx.accounts.signer.key() == escrow.initiator &&
+            escrow.in_cooling_off(&ctx.accounts.config)?;
+        
+        // A disputed escrow's funds only move through the arbiter's settlement, so neither
+        // party can pull them out from under the dispute
+        require!(escrow.status != EscrowStatus::Disputed, EscrowError::EscrowDisputed);
+        require!(
+            !escrow.milestones.iter().any(|m| m.status == MilestoneStatus::Disputed),
+            EscrowError::EscrowDisputed
+        );
+        // Funded or partially released; only the remainder is refunded
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(cooling_off || escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(
+            ctx.accounts.signer.key() == escrow.arbiter ||
+            ctx.accounts.signer.key() == escrow.initiator,
+            EscrowError::Unauthorized
+        );
+        // Without an arbiter to check a clawback, the initiator may only cancel before any release
+        require!(
+            escrow.has_arbiter() || escrow.released_amount == 0,
+            EscrowError::InvalidEscrowStatus
+        );
+
+        // Only return unreleased funds; for SOL that is whatever the vault holds above its
+        // rent floor, which also covers lamports sent to the vault outside the program
+        let mut remaining_amount = match escrow.deal_type {
+            DealType::Sol => {
+                ctx.accounts.validate_deal_accounts()?;
+                Escrow::sol_vault_available(&ctx.accounts.escrow_sol_vault)?
+            },
+            DealType::Forge => escrow.amount - escrow.released_amount,
+        };
+
+        if ctx.accounts.signer.key() == escrow.arbiter {
+            let rebate = escrow.arbiter_gas_rebate(&ctx.accounts.config, remaining_amount);
+            if rebate > 0 {
+                ctx.accounts.refund(ctx.accounts.signer.to_account_info(), rebate, None)?;
+                remaining_amount -= rebate;
+            }
+        }
+
+        if remaining_amount > 0 {
+            ctx.accounts.refund_remaining(remaining_amount, ctx.remaining_accounts)?;
+        }
+        // Lamports sent to the vault outside the program were never counted as locked
+        let unlocked_amount = escrow.amount - escrow.released_amount;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, unlocked_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        for milestone in escrow.milestones.iter_mut() {
+            if milestone.status == MilestoneStatus::Pending {
+                milestone.status = MilestoneStatus::Cancelled;
+            }
+        }
+
+        escrow.status = EscrowStatus::Cancelled;
+        escrow.stamp_finalized()?;
+        msg!("Escrow cancelled for ID: {}", escrow.escrow_id);
+        Ok(())
+    }
+
+    // Restart a deal under a new arbiter and terms without the funds leaving the program:
+    // cancel the escrow and move what it still holds straight into a new escrow of the same
+    // initiator. The new escrow is held to the same creation rules as initialize_escrow. The
+    // deal type, funding fee, referrer, funding time and fee accounting carry over.
+    // remaining_accounts: the mint's transfer-hook accounts, when it has one
+    pub fn cancel_and_reinit<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelAndReinit<'info>>,
+        new_escrow_id: u64,
+        arbiter: Option<Pubkey>, // None for a two-party deal settled by the initiator or a schedule
+        recipient: Pubkey,
+        expires_at: i64, // After this the initiator can claim the funds back (0 = never expires)
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let config = &ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+
+        // Same conditions as cancel_escrow, minus the cooling-off exemption
+        require!(escrow.status != EscrowStatus::Disputed, EscrowError::EscrowDisputed);
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(
+            escrow.has_arbiter() || escrow.released_amount == 0,
+            EscrowError::InvalidEscrowStatus
+        );
+        // Milestone plans and contributors' shares have no place under the new terms
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(escrow.contributions.is_empty(), EscrowError::InvalidContributor);
+        config.validate_new_escrow(
+            &escrow.initiator,
+            arbiter,
+            expires_at,
+            &escrow.deal_type,
+            &escrow.mint,
+            now,
+        )?;
+        RecipientWhitelist::check(&ctx.accounts.recipient_whitelist, &recipient)?;
+        require!(
+            Escrow::sol_vault_available(&ctx.accounts.new_escrow_sol_vault)? == 0,
+            EscrowError::VaultNotEmpty
+        );
+
+        // Everything a cancel would have refunded moves across instead
+        let carried_amount = match escrow.deal_type {
+            DealType::Sol => Escrow::sol_vault_available(&ctx.accounts.escrow_sol_vault)?,
+            DealType::Forge => escrow.amount - escrow.released_amount,
+        };
+        require!(carried_amount > 0, EscrowError::NoFundsToRelease);
+        UserStats::record_deposit(
+            ctx.accounts.user_stats.as_deref_mut(),
+            config,
+            &escrow.deal_type,
+            carried_amount,
+            now,
+        )?;
+
+        match escrow.deal_type {
+            DealType::Sol => {
+                let vault = ctx.accounts.escrow_sol_vault.to_account_info();
+                **vault.try_borrow_mut_lamports()? -= carried_amount;
+                **ctx.accounts.new_escrow_sol_vault.try_borrow_mut_lamports()? += carried_amount;
+            },
+            DealType::Forge => {
+                require_keys_eq!(
+                    ctx.accounts.escrow_token_vault.owner,
+                    escrow.key(),
+                    EscrowError::InvalidDealType
+                );
+                let transfer_hook = escrow.transfer_hook(&ctx.accounts.forge_mint, ctx.remaining_accounts);
+                Escrow::handle_forge_refund(
+                    ctx.accounts.escrow_token_vault.to_account_info(),
+                    ctx.accounts.new_escrow_token_vault.to_account_info(),
+                    ctx.accounts.token_program.to_account_info(),
+                    escrow.to_account_info(),
+                    carried_amount,
+                    escrow.bump,
+                    escrow.escrow_id,
+                    transfer_hook.as_ref(),
+                )?;
+            }
+        }
+
+        let new_escrow = &mut ctx.accounts.new_escrow;
+        new_escrow.escrow_id = new_escrow_id;
+        new_escrow.initiator = escrow.initiator;
+        new_escrow.recipient = recipient;
+        new_escrow.arbiter = arbiter.unwrap_or_default();
+        new_escrow.amount = carried_amount;
+        new_escrow.released_amount = 0;
+        new_escrow.deal_type = escrow.deal_type.clone();
+        new_escrow.status = EscrowStatus::Funded;
+        new_escrow.bump = ctx.bumps.new_escrow;
+        new_escrow.sol_vault_bump = ctx.bumps.new_escrow_sol_vault;
+        new_escrow.fee_at_funding = escrow.fee_at_funding;
+        new_escrow.expires_at = expires_at;
+        new_escrow.expiry_charges_fee = escrow.expiry_charges_fee;
+        new_escrow.referrer = escrow.referrer;
+        new_escrow.referrer_bps = escrow.referrer_bps;
+        new_escrow.schema_version = ESCROW_SCHEMA_VERSION;
+        new_escrow.funded_at = escrow.funded_at;
+        new_escrow.token_program = escrow.token_program;
+        new_escrow.mint = escrow.mint;
+        new_escrow.fees_paid = escrow.fees_paid;
+        new_escrow.fee_cap_absolute = escrow.fee_cap_absolute;
+        if let Some(user_index) = ctx.accounts.user_index.as_mut() {
+            user_index.register(new_escrow_id)?;
+        }
+
+        // The value stays locked, just under the new escrow
+        let deal_type = escrow.deal_type.clone();
+        let unlocked_amount = escrow.amount - escrow.released_amount;
+        ctx.accounts.config.unlock_value(&deal_type, unlocked_amount);
+        ctx.accounts.config.lock_value(&deal_type, carried_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.status = EscrowStatus::Cancelled;
+        escrow.stamp_finalized()?;
+        msg!(
+            "Escrow ID: {} cancelled and its {} carried into escrow ID: {}",
+            escrow.escrow_id,
+            carried_amount,
+            new_escrow_id
+        );
+        Ok(())
+    }
+
+    // Cancel a percentage of the remaining funds, refunding contributors proportionally and
+    // the initiator the rest; the escrow stays open for the remainder.
+    // remaining_accounts: one payee per contribution, in order (wallet for SOL, token account for FORGE),
+    // followed by the mint's transfer-hook accounts when it has one
+    pub fn partial_cancel<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelEscrow<'info>>,
+        percentage: u8, // Percentage of the remaining funds to cancel (1-100)
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(
+            ctx.accounts.signer.key() == escrow.arbiter ||
+            ctx.accounts.signer.key() == escrow.initiator,
+            EscrowError::Unauthorized
+        );
+        require!(
+            escrow.has_arbiter() || escrow.released_amount == 0,
+            EscrowError::InvalidEscrowStatus
+        );
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(percentage > 0, EscrowError::PercentageIsZero);
+        require!(percentage <= 100, EscrowError::PercentageTooHigh);
+
+        let cancel_amount = escrow.percentage_of_remaining(percentage)?;
+
+        ctx.accounts.refund_remaining(cancel_amount, ctx.remaining_accounts)?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, cancel_amount);
+
+        // Contributions shrink with the total so later refunds keep the same proportions
+        let escrow = &mut ctx.accounts.escrow;
+        let shares: Vec<u64> = escrow
+            .contributions
+            .iter()
+            .map(|contribution| escrow.contribution_share(cancel_amount, contribution))
+            .collect();
+        for (contribution, share) in escrow.contributions.iter_mut().zip(shares) {
+            contribution.amount -= share;
+        }
+        escrow.amount -= cancel_amount;
+
+        if escrow.released_amount >= escrow.amount {
+            escrow.status = EscrowStatus::Cancelled;
+            escrow.stamp_finalized()?;
+        }
+
+        msg!(
+            "Partial cancel ({}%) for escrow ID: {}. Refunded: {}",
+            percentage,
+            escrow.escrow_id,
+            cancel_amount
+        );
+        Ok(())
+    }
+
+    // Initiator takes back what they over-deposited before anything is released. Only their
+    // own share can be withdrawn; third-party contributions stay in the escrow.
+    // remaining_accounts: the mint's transfer-hook accounts, when it has one
+    pub fn withdraw_overfund<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelEscrow<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(escrow.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(escrow.paused_by.is_none(), EscrowError::EscrowPaused);
+        require!(ctx.accounts.signer.key() == escrow.initiator, EscrowError::Unauthorized);
+        require!(escrow.milestones.is_empty(), EscrowError::MilestoneEscrow);
+        require!(amount > 0, EscrowError::InsufficientFunds);
+
+        let third_party: u64 = escrow
+            .contributions
+            .iter()
+            .filter(|contribution| contribution.contributor != escrow.initiator)
+            .map(|contribution| contribution.amount)
+            .sum();
+        let floor = third_party.max(escrow.released_amount);
+        require!(
+            amount < escrow.amount && escrow.amount - amount > floor,
+            EscrowError::WithdrawExceedsOverfund
+        );
+
+        let transfer_hook = ctx.accounts.transfer_hook(ctx.remaining_accounts);
+        ctx.accounts.refund_initiator(amount, transfer_hook.as_ref())?;
+        ctx.accounts.config.unlock_value(&ctx.accounts.escrow.deal_type, amount);
+
+        // Top-ups the initiator made through contribute are withdrawn first
+        let escrow = &mut ctx.accounts.escrow;
+        let initiator = escrow.initiator;
+        if let Some(own) = escrow.contributions.iter_mut().find(|c| c.contributor == initiator) {
+            own.amount = own.amount.saturating_sub(amount);
+        }
+        escrow.contributions.retain(|contribution| contribution.amount > 0);
+        escrow.amount -= amount;
+
+        msg!(
+            "Withdrew overfund of {} from escrow ID: {}. Amount now: {}",
+            amount,
+            escrow.escrow_id,
+            escrow.amount
+        );
+        Ok(())
+    }
+
+    // Whether close_escrow would go through right now: the escrow is finished, its read window
+    // has passed and, for FORGE deals, its token vault is empty
+    pub fn can_close(ctx: Context<CanClose>) -> Result<bool> {
+        let now = Clock::get()?.unix_timestamp;
+        Ok(ctx
+            .accounts
+            .escrow
+            .check_closable(&ctx.accounts.config, &ctx.accounts.escrow_token_vault, now)
+            .is_ok())
+    }
+
+    // Close a finished escrow account and its SOL vault and return their rent to the
+    // initiator, once the configured read window after finalization has passed; any dust left
+    // in the vault above its rent goes to the treasury. Passing a tombstone keeps a minimal
+    // permanent record of the outcome.
+    pub fn close_escrow(ctx: Context<CloseEscrow>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let now = Clock::get()?.unix_timestamp;
+
+        escrow.check_closable(&ctx.accounts.config, &ctx.accounts.escrow_token_vault, now)?;
+
+        if let Some(tombstone) = ctx.accounts.tombstone.as_mut() {
+            tombstone.escrow_id = escrow.escrow_id;
+            tombstone.status = escrow.status.clone();
+            tombstone.released_amount = escrow.released_amount;
+            tombstone.fees_paid = escrow.fees_paid;
+            tombstone.closed_at = now;
+            tombstone.bump = ctx.bumps.tombstone;
+        }
+
+        // The SOL vault goes with the escrow, so a reused escrow_id starts with a fresh one
+        let (rent, dust) = Escrow::drain_sol_vault(
+            &ctx.accounts.escrow_sol_vault,
+            &ctx.accounts.initiator.to_account_info(),
+            &ctx.accounts.treasury,
+        )?;
+
+        msg!(
+            "Escrow ID: {} closed. Vault rent returned: {}, dust to treasury: {}",
+            escrow.escrow_id,
+            rent,
+            dust
+        );
+        Ok(())
+    }
+
+    // Close the emptied SOL vault of a finished escrow and return its rent to the initiator;
+    // any dust left above the rent goes to the treasury. The escrow account itself stays open.
+    pub fn close_sol_vault(ctx: Context<CloseSolVault>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(
+            matches!(escrow.status, EscrowStatus::Cancelled | EscrowStatus::Released),
+            EscrowError::InvalidEscrowStatus
+        );
+        require!(escrow.deal_type == DealType::Sol, EscrowError::InvalidDealType);
+
+        let (rent, dust) = Escrow::drain_sol_vault(
+            &ctx.accounts.escrow_sol_vault,
+            &ctx.accounts.initiator.to_account_info(),
+            &ctx.accounts.treasury,
+        )?;
+
+        msg!(
+            "SOL vault closed for escrow ID: {}. Rent returned: {}, dust to treasury: {}",
+            escrow.escrow_id,
+            rent,
+            dust
+        );
+        Ok(())
+    }
+
+    // Admin cleanup for escrows finalized before vaults were closed on release: closes the
+    // stranded SOL vault of any released or cancelled escrow, FORGE deals included, and
+    // returns its rent to the initiator and any dust to the treasury
+    pub fn sweep_stranded_vault(ctx: Context<SweepStrandedVault>, escrow_id: u64) -> Result<()> {
+        let (initiator, status) = Escrow::read_prefix(&ctx.accounts.escrow)?;
+
+        require_keys_eq!(ctx.accounts.initiator.key(), initiator, EscrowError::Unauthorized);
+        require!(
+            matches!(status, EscrowStatus::Cancelled | EscrowStatus::Released),
+            EscrowError::InvalidEscrowStatus
+        );
+        require!(ctx.accounts.escrow_sol_vault.lamports() > 0, EscrowError::VaultAlreadyClosed);
+
+        let (rent, dust) = Escrow::drain_sol_vault(
+            &ctx.accounts.escrow_sol_vault,
+            &ctx.accounts.initiator,
+            &ctx.accounts.treasury,
+        )?;
+
+        msg!(
+            "Stranded SOL vault swept for escrow ID: {}. Rent returned: {}, dust to treasury: {}",
+            escrow_id,
+            rent,
+            dust
+        );
+        Ok(())
+    }
+
+    // Arbiter settles a dispute in one go: pay the recipient their share of the remaining
+    // funds (fee applies to that share only) and refund the rest to the initiator
+    pub fn settle(ctx: Context<Settle>, recipient_bps: u16) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(
+            escrow.is_open() || escrow.status == EscrowStatus::Disputed,
+            EscrowError::InvalidEscrowStatus
+        );
+        // A pause holds the parties' own releases and cancels, not the arbiter's resolution
+        require!(ctx.accounts.signer.key() == escrow.arbiter, EscrowError::Unauthorized);
+        escrow.check_require_both(&ctx.accounts.signer.key())?;
+        require!(recipient_bps <= 10_000, EscrowError::InvalidPercentage);
+        // Contributor refunds go through cancel_escrow
+        require!(escrow.contributions.is_empty(), EscrowError::InvalidContributor);
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        require!(remaining_amount > 0, EscrowError::NoFundsToRelease);
+
+        // The arbiter's gas rebate comes off the top; the rest is split
+        let rebate = escrow.arbiter_gas_rebate(&ctx.accounts.config, remaining_amount);
+        let settled_amount = remaining_amount - rebate;
+
+        let recipient_amount = (settled_amount as u128 * recipient_bps as u128 / 10_000) as u64;
+        let refund_amount = settled_amount - recipient_amount;
+        ctx.accounts.pay_out(recipient_amount, refund_amount, rebate)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.status = if recipient_amount > 0 {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::Cancelled
+        };
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "Escrow ID: {} settled. Recipient: {} ({} bps), Refunded: {}",
+            escrow.escrow_id,
+            recipient_amount,
+            recipient_bps,
+            refund_amount
+        );
+        Ok(())
+    }
+
+    // Arbiter closes the deal: pay the recipient a fixed amount (fee applies to it), refund
+    // everything else to the initiator and mark the escrow Released
+    pub fn finalize(ctx: Context<Settle>, recipient_amount: u64) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        require!(
+            escrow.is_open() || escrow.status == EscrowStatus::Disputed,
+            EscrowError::InvalidEscrowStatus
+        );
+        // A pause holds the parties' own releases and cancels, not the arbiter's resolution
+        require!(ctx.accounts.signer.key() == escrow.arbiter, EscrowError::Unauthorized);
+        escrow.check_require_both(&ctx.accounts.signer.key())?;
+        // Contributor refunds go through cancel_escrow
+        require!(escrow.contributions.is_empty(), EscrowError::InvalidContributor);
+        require!(recipient_amount > 0, EscrowError::NoFundsToRelease);
+
+        let remaining_amount = escrow.amount - escrow.released_amount;
+        let rebate = escrow.arbiter_gas_rebate(&ctx.accounts.config, remaining_amount);
+        require!(
+            recipient_amount <= remaining_amount - rebate,
+            EscrowError::InsufficientFunds
+        );
+
+        let refund_amount = remaining_amount - rebate - recipient_amount;
+        ctx.accounts.pay_out(recipient_amount, refund_amount, rebate)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.status = EscrowStatus::Released;
+        escrow.stamp_finalized()?;
+
+        msg!(
+            "Escrow ID: {} finalized. Recipient: {}, Refunded: {}",
+            escrow.escrow_id,
+            recipient_amount,
+            refund_amount
+        );
+        Ok(())
+    }
+
+    // Create a shared account holding many small SOL escrows, so they share one rent deposit
+    pub fn create_escrow_pool(ctx: Context<CreateEscrowPool>, pool_id: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        pool.pool_id = pool_id;
+        pool.bump = ctx.bumps.pool;
+        pool.entries = Vec::new();
+
+        msg!("Escrow pool created with ID: {}", pool_id);
+        Ok(())
+    }
+
+    // Add a SOL micro-escrow to a pool and deposit its funds into the pool account
+    pub fn add_pooled_escrow(
+        ctx: Context<AddPooledEscrow>,
+        amount: u64,
+        arbiter: Pubkey,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        require!(amount > 0, EscrowError::InsufficientFunds);
+        require!(
+            ctx.accounts.pool.entries.len() < MAX_POOL_ENTRIES,
+            EscrowError::PoolFull
+        );
+        // A pooled escrow is created under the same rules as a standalone one
+        let initiator = ctx.accounts.initiator.key();
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.config.validate_new_escrow(
+            &initiator,
+            Some(arbiter),
+            0,
+            &DealType::Sol,
+            &Pubkey::default(),
+            now,
+        )?;
+        RecipientWhitelist::check(&ctx.accounts.recipient_whitelist, &recipient)?;
+        UserStats::record_deposit(
+            ctx.accounts.user_stats.as_deref_mut(),
+            &ctx.accounts.config,
+            &DealType::Sol,
+            amount,
+            now,
+        )?;
+
+        let transfer_instruction = system_instruction::transfer(
+            &ctx.accounts.initiator.key(),
+            &ctx.accounts.pool.key(),
+            amount,
+        );
+        invoke(
+            &transfer_instruction,
+            &[
+                ctx.accounts.initiator.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        ctx.accounts.config.lock_value(&DealType::Sol, amount);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.entries.push(PooledEscrow {
+            initiator: ctx.accounts.initiator.key(),
+            recipient,
+            arbiter,
+            amount,
+            status: EscrowStatus::Funded,
+        });
+
+        msg!(
+            "Pooled escrow {} added to pool ID: {}, Amount: {}",
+            pool.entries.len() - 1,
+            pool.pool_id,
+            amount
+        );
+        Ok(())
+    }
+
+    // Release a single pooled escrow to its recipient with the usual 10% fee
+    pub fn release_pooled_escrow(ctx: Context<ReleasePooledEscrow>, index: u8) -> Result<()> {
+        let entry = ctx
+            .accounts
+            .pool
+            .entries
+            .get(index as usize)
+            .ok_or(EscrowError::InvalidPoolEntry)?
+            .clone();
+
+        require!(entry.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(
+            ctx.accounts.signer.key() == entry.arbiter ||
+            ctx.accounts.signer.key() == entry.initiator,
+            EscrowError::Unauthorized
+        );
+        require_keys_eq!(ctx.accounts.recipient.key(), entry.recipient, EscrowError::Unauthorized);
+
+        let (net_release_amount, half_fee) =
+            Escrow::split_fee_bps(entry.amount, ctx.accounts.config.capped_fee_bps(DEFAULT_FEE_BPS));
+        Escrow::handle_sol_release(
+            ctx.accounts.pool.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
+            ctx.accounts.fee_wallet.to_account_info(),
+            ctx.accounts.temp_fee_wallet.to_account_info(),
+            net_release_amount,
+            half_fee,
+        )?;
+        ctx.accounts.config.unlock_value(&DealType::Sol, entry.amount);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.entries[index as usize].status = EscrowStatus::Released;
+
+        msg!("Pooled escrow {} released from pool ID: {}", index, pool.pool_id);
+        Ok(())
+    }
+
+    // Cancel a single pooled escrow and return its funds to the initiator
+    pub fn cancel_pooled_escrow(ctx: Context<CancelPooledEscrow>, index: u8) -> Result<()> {
+        let entry = ctx
+            .accounts
+            .pool
+            .entries
+            .get(index as usize)
+            .ok_or(EscrowError::InvalidPoolEntry)?
+            .clone();
+
+        require!(entry.status == EscrowStatus::Funded, EscrowError::InvalidEscrowStatus);
+        require!(
+            ctx.accounts.signer.key() == entry.arbiter ||
+            ctx.accounts.signer.key() == entry.initiator,
+            EscrowError::Unauthorized
+        );
+        require_keys_eq!(ctx.accounts.initiator.key(), entry.initiator, EscrowError::Unauthorized);
+
+        **ctx.accounts.pool.to_account_info().try_borrow_mut_lamports()? -= entry.amount;
+        **ctx.accounts.initiator.to_account_info().try_borrow_mut_lamports()? += entry.amount;
+        ctx.accounts.config.unlock_value(&DealType::Sol, entry.amount);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.entries[index as usize].status = EscrowStatus::Cancelled;
+
+        msg!("Pooled escrow {} cancelled in pool ID: {}", index, pool.pool_id);
+        Ok(())
+    }
+
+    // Create the global program config; the caller becomes its admin
+    pub fn initialize_config(ctx: Context<InitializeConfig>, treasury: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = ctx.accounts.admin.key();
+        config.treasury = treasury;
+        config.max_allowed_fee_bps = DEFAULT_FEE_BPS;
+        config.bump = ctx.bumps.config;
+
+        msg!("Config initialized with admin: {}, treasury: {}", config.admin, treasury);
+        Ok(())
+    }
+
+    // Admin updates the treasury that receives platform fees
+    pub fn update_config(ctx: Context<UpdateConfig>, treasury: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.treasury = treasury;
+
+        msg!("Config treasury updated to: {}", treasury);
+        Ok(())
+    }
+
+    // Admin sets the Pyth SOL/USD feed used to settle USD-denominated escrows
+    pub fn set_price_feed(ctx: Context<UpdateConfig>, price_feed: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.price_feed = price_feed;
+
+        msg!("Config price feed set to: {}", price_feed);
+        Ok(())
+    }
+
+    // Admin sets the Pyth FORGE/USD price feed used by release_usd
+    pub fn set_token_price_feed(ctx: Context<UpdateConfig>, token_price_feed: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.token_price_feed = token_price_feed;
+
+        msg!("Config token price feed set to: {}", token_price_feed);
+        Ok(())
+    }
+
+    // Admin replaces the alternate token programs (e.g. Token-2022) new escrows may use
+    pub fn set_token_program_allowlist(
+        ctx: Context<UpdateConfig>,
+        token_programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            token_programs.len() <= MAX_TOKEN_PROGRAMS,
+            EscrowError::TooManyTokenPrograms
+        );
+
+        config.token_program_allowlist = token_programs;
+
+        msg!("Token program allowlist updated: {} programs", config.token_program_allowlist.len());
+        Ok(())
+    }
+
+    // Admin turns permissioned mode (initiator allowlist enforcement) on or off
+    pub fn set_permissioned(ctx: Context<UpdateConfig>, permissioned: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.permissioned = permissioned;
+
+        msg!("Config permissioned mode set to: {}", permissioned);
+        Ok(())
+    }
+
+    // Admin lets initiators arbitrate their own escrows, for test and dev deployments only
+    pub fn set_allow_self_arbiter(ctx: Context<UpdateConfig>, allow_self_arbiter: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.allow_self_arbiter = allow_self_arbiter;
+
+        msg!("Config self-arbiter escrows allowed: {}", allow_self_arbiter);
+        Ok(())
+    }
+
+    // Admin nominates a successor; nothing changes until the successor accepts
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.pending_admin = Some(new_admin);
+
+        msg!("Config admin proposed: {}", new_admin);
+        Ok(())
+    }
+
+    // The proposed admin signs to take over, proving the key is reachable
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            config.pending_admin == Some(ctx.accounts.new_admin.key()),
+            EscrowError::Unauthorized
+        );
+
+        config.admin = ctx.accounts.new_admin.key();
+        config.pending_admin = None;
+
+        msg!("Config admin accepted: {}", config.admin);
+        Ok(())
+    }
+
+    // Admin allows a wallet to create escrows in permissioned mode
+    pub fn add_allowed_initiator(ctx: Context<UpdateConfig>, initiator: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        if !config.initiator_allowlist.contains(&initiator) {
+            require!(
+                config.initiator_allowlist.len() < MAX_ALLOWED_INITIATORS,
+                EscrowError::AllowlistFull
+            );
+            config.initiator_allowlist.push(initiator);
+        }
+
+        msg!("Initiator allowed: {}", initiator);
+        Ok(())
+    }
+
+    // Admin sets volume-based fee discounts applied by release_funds
+    pub fn set_fee_tiers(ctx: Context<UpdateConfig>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(fee_tiers.len() <= MAX_FEE_TIERS, EscrowError::InvalidFeeTiers);
+        require!(
+            fee_tiers.iter().all(|tier| tier.fee_bps <= config.max_allowed_fee_bps),
+            EscrowError::FeeTooHigh
+        );
+
+        config.fee_tiers = fee_tiers;
+
+        msg!("Fee tiers set: {}", config.fee_tiers.len());
+        Ok(())
+    }
+
+    // Create the volume tracker that fee tiers are based on
+    pub fn initialize_user_stats(ctx: Context<InitializeUserStats>) -> Result<()> {
+        let user_stats = &mut ctx.accounts.user_stats;
+
+        user_stats.user = ctx.accounts.user.key();
+        user_stats.bump = ctx.bumps.user_stats;
+
+        msg!("User stats initialized for: {}", user_stats.user);
+        Ok(())
+    }
+
+    // Create the index a user's new escrows are listed in, when passed to initialize_escrow
+    pub fn initialize_user_index(ctx: Context<InitializeUserIndex>) -> Result<()> {
+        let user_index = &mut ctx.accounts.user_index;
+
+        user_index.user = ctx.accounts.user.key();
+        user_index.bump = ctx.bumps.user_index;
+
+        msg!("User index initialized for: {}", user_index.user);
+        Ok(())
+    }
+
+    // Initiator sets which recipients their escrows may pay, e.g. approved vendors, creating
+    // the whitelist on first use. Disabling it keeps the list for later.
+    pub fn set_recipient_whitelist(
+        ctx: Context<SetRecipientWhitelist>,
+        enabled: bool,
+        recipients: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            recipients.len() <= MAX_WHITELISTED_RECIPIENTS,
+            EscrowError::AllowlistFull
+        );
+
+        let whitelist = &mut ctx.accounts.recipient_whitelist;
+        whitelist.owner = ctx.accounts.owner.key();
+        whitelist.enabled = enabled;
+        whitelist.recipients = recipients;
+        whitelist.bump = ctx.bumps.recipient_whitelist;
+
+        msg!(
+            "Recipient whitelist for {} {}: {} recipients",
+            whitelist.owner,
+            if enabled { "enabled" } else { "disabled" },
+            whitelist.recipients.len()
+        );
+        Ok(())
+    }
+
+    // Register a short referral code that escrows can be created with in place of the
+    // signer's address; first come, first served
+    pub fn register_referral_code(ctx: Context<RegisterReferralCode>, code: String) -> Result<()> {
+        require!(
+            !code.is_empty() && code.len() <= MAX_REFERRAL_CODE_LEN,
+            EscrowError::InvalidReferralCode
+        );
+
+        let referral_code = &mut ctx.accounts.referral_code;
+        referral_code.referrer = ctx.accounts.referrer.key();
+        referral_code.code = code;
+        referral_code.bump = ctx.bumps.referral_code;
+
+        msg!("Referral code {} registered for: {}", referral_code.code, referral_code.referrer);
+        Ok(())
+    }
+
+    // Create the accumulator SOL releases can set their buy-and-burn half aside in
+    pub fn initialize_pending_burn(ctx: Context<InitializePendingBurn>) -> Result<()> {
+        let pending_burn = &mut ctx.accounts.pending_burn;
+
+        pending_burn.total_processed = 0;
+        pending_burn.bump = ctx.bumps.pending_burn;
+
+        msg!("Pending burn accumulator created");
+        Ok(())
+    }
+
+    // Admin's keeper processes everything accumulated for buy-and-burn in one go
+    pub fn process_pending_burn(ctx: Context<ProcessPendingBurn>) -> Result<()> {
+        let pending_burn = ctx.accounts.pending_burn.to_account_info();
+        let amount = Escrow::sol_vault_available(&pending_burn)?;
+        require!(amount > 0, EscrowError::NothingToBurn);
+
+        // TODO: Buy Forge token from dex with the accumulated SOL and burn it
+        // For now, sending it to the temp fee wallet as single releases did
+        **pending_burn.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.temp_fee_wallet.try_borrow_mut_lamports()? += amount;
+
+        let pending_burn = &mut ctx.accounts.pending_burn;
+        pending_burn.total_processed += amount;
+
+        msg!("Pending burn processed: {}. Total: {}", amount, pending_burn.total_processed);
+        Ok(())
+    }
+
+    pub fn initialize_insurance_pool(ctx: Context<InitializeInsurancePool>) -> Result<()> {
+        let insurance_pool = &mut ctx.accounts.insurance_pool;
+
+        insurance_pool.total_claimed = 0;
+        insurance_pool.claim_amount = 0;
+        insurance_pool.claim_destination = Pubkey::default();
+        insurance_pool.claim_unlocks_at = 0;
+        insurance_pool.bump = ctx.bumps.insurance_pool;
+
+        msg!("Insurance pool created");
+        Ok(())
+    }
+
+    // Admin sets the share of SOL release fees routed to the insurance pool, in basis points
+    pub fn set_insurance_bps(ctx: Context<UpdateConfig>, insurance_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(insurance_bps <= 10_000, EscrowError::InvalidInsuranceBps);
+
+        config.insurance_bps = insurance_bps;
+
+        msg!("Insurance share set to: {} bps", insurance_bps);
+        Ok(())
+    }
+
+    // Admin announces a payout from the insurance pool; it can only be claimed once the
+    // timelock has passed, giving users time to see it coming
+    pub fn request_insurance_claim(ctx: Context<ClaimInsurance>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let available = Escrow::sol_vault_available(&ctx.accounts.insurance_pool.to_account_info())?;
+        require!(amount > 0 && amount <= available, EscrowError::InsufficientFunds);
+
+        let insurance_pool = &mut ctx.accounts.insurance_pool;
+        insurance_pool.claim_amount = amount;
+        insurance_pool.claim_destination = ctx.accounts.destination.key();
+        insurance_pool.claim_unlocks_at = now + INSURANCE_CLAIM_TIMELOCK_SECS;
+
+        msg!(
+            "Insurance claim of {} to {} unlocks at {}",
+            amount,
+            insurance_pool.claim_destination,
+            insurance_pool.claim_unlocks_at
+        );
+        Ok(())
+    }
+
+    // Admin pays out the announced insurance claim after its timelock
+    pub fn claim_insurance(ctx: Context<ClaimInsurance>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let insurance_pool = &ctx.accounts.insurance_pool;
+        let amount = insurance_pool.claim_amount;
+
+        require!(amount > 0, EscrowError::NoInsuranceClaim);
+        require!(now >= insurance_pool.claim_unlocks_at, EscrowError::InsuranceClaimLocked);
+        require_keys_eq!(
+            ctx.accounts.destination.key(),
+            insurance_pool.claim_destination,
+            EscrowError::Unauthorized
+        );
+        require!(
+            amount <= Escrow::sol_vault_available(&insurance_pool.to_account_info())?,
+            EscrowError::InsufficientFunds
+        );
+
+        **ctx.accounts.insurance_pool.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.destination.try_borrow_mut_lamports()? += amount;
+
+        let insurance_pool = &mut ctx.accounts.insurance_pool;
+        insurance_pool.total_claimed += amount;
+        insurance_pool.claim_amount = 0;
+
+        msg!("Insurance claim paid: {}. Total claimed: {}", amount, insurance_pool.total_claimed);
+        Ok(())
+    }
+
+    // Admin sets the lamports reimbursed to arbiters who cancel or settle SOL escrows
+    pub fn set_arbiter_gas_rebate(ctx: Context<UpdateConfig>, arbiter_gas_rebate: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            arbiter_gas_rebate <= MAX_ARBITER_GAS_REBATE,
+            EscrowError::GasRebateTooHigh
+        );
+
+        config.arbiter_gas_rebate = arbiter_gas_rebate;
+
+        msg!("Arbiter gas rebate set to: {}", arbiter_gas_rebate);
+        Ok(())
+    }
+
+    // Admin caps the total fee any single release of an escrow pays (0 = uncapped); the
+    // excess over the cap goes to the recipient
+    pub fn set_fee_cap(ctx: Context<SetFeeCap>, fee_cap_absolute: u64) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(escrow.is_open(), EscrowError::InvalidEscrowStatus);
+
+        escrow.fee_cap_absolute = fee_cap_absolute;
+
+        msg!("Escrow ID: {} fee capped at {}", escrow.escrow_id, fee_cap_absolute);
+        Ok(())
+    }
+
+    // Admin caps the fee rate any release may charge, at most the default fee; 0 waives fees
+    pub fn set_max_allowed_fee_bps(ctx: Context<UpdateConfig>, max_allowed_fee_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(max_allowed_fee_bps <= DEFAULT_FEE_BPS, EscrowError::FeeTooHigh);
+
+        config.max_allowed_fee_bps = max_allowed_fee_bps;
+
+        msg!("Max allowed fee set to: {} bps", config.max_allowed_fee_bps);
+        Ok(())
+    }
+
+    // Admin sets the fee discount for patient escrows: it grows linearly from nothing at
+    // funding to `max_fee_discount_bps` of the fee once `fee_discount_window` seconds have passed
+    pub fn set_fee_discount(
+        ctx: Context<UpdateConfig>,
+        fee_discount_window: i64,
+        max_fee_discount_bps: u16,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(fee_discount_window >= 0, EscrowError::InvalidDeadline);
+        require!(max_fee_discount_bps <= 10_000, EscrowError::InvalidFeeDiscount);
+
+        config.fee_discount_window = fee_discount_window;
+        config.max_fee_discount_bps = max_fee_discount_bps;
+
+        msg!(
+            "Fee discount set to: up to {} bps over {}s",
+            max_fee_discount_bps,
+            fee_discount_window
+        );
+        Ok(())
+    }
+
+    // Admin sets how long finished escrows stay readable before they can be closed
+    pub fn set_close_grace_period(ctx: Context<UpdateConfig>, close_grace_period: i64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(close_grace_period >= 0, EscrowError::InvalidDeadline);
+
+        config.close_grace_period = close_grace_period;
+
+        msg!("Close grace period set to: {}s", close_grace_period);
+        Ok(())
+    }
+
+    // Admin sets how long after funding initiators may cancel regardless of pauses
+    // (0 = no cooling-off window)
+    pub fn set_cooling_off_period(ctx: Context<UpdateConfig>, cooling_off_period: i64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(cooling_off_period >= 0, EscrowError::InvalidDeadline);
+
+        config.cooling_off_period = cooling_off_period;
+
+        msg!("Cooling-off period set to: {}s", cooling_off_period);
+        Ok(())
+    }
+
+    // Admin caps how far out an escrow's expiry may be set (0 = no cap)
+    pub fn set_max_deadline_duration(
+        ctx: Context<UpdateConfig>,
+        max_deadline_duration: i64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(max_deadline_duration >= 0, EscrowError::InvalidDeadline);
+
+        config.max_deadline_duration = max_deadline_duration;
+
+        msg!("Max deadline duration set to: {}s", max_deadline_duration);
+        Ok(())
+    }
+
+    // Admin rate-limits how much one initiator can lock per window (0 window or limit = no limit)
+    pub fn set_deposit_limit(
+        ctx: Context<UpdateConfig>,
+        deposit_window: i64,
+        deposit_limit_per_window_sol: u64,
+        deposit_limit_per_window_token: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(deposit_window >= 0, EscrowError::InvalidDeadline);
+
+        config.deposit_window = deposit_window;
+        config.deposit_limit_per_window_sol = deposit_limit_per_window_sol;
+        config.deposit_limit_per_window_token = deposit_limit_per_window_token;
+
+        msg!(
+            "Deposit limit set to: {} lamports / {} tokens per {}s",
+            deposit_limit_per_window_sol,
+            deposit_limit_per_window_token,
+            deposit_window
+        );
+        Ok(())
+    }
+
+    // Admin sets the remainders below which a release sweeps the rest of the escrow (0 = off)
+    pub fn set_dust_threshold(
+        ctx: Context<UpdateConfig>,
+        dust_threshold_sol: u64,
+        dust_threshold_token: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.dust_threshold_sol = dust_threshold_sol;
+        config.dust_threshold_token = dust_threshold_token;
+
+        msg!(
+            "Dust threshold set to: {} lamports / {} tokens",
+            dust_threshold_sol,
+            dust_threshold_token
+        );
+        Ok(())
+    }
+
+    // Admin sets which mints token escrows may be created in (empty = any mint)
+    pub fn set_mint_allowlist(ctx: Context<UpdateConfig>, mints: Vec<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(mints.len() <= MAX_ALLOWED_MINTS, EscrowError::AllowlistFull);
+        config.mint_allowlist = mints;
+
+        msg!("Mint allowlist set: {} mints", config.mint_allowlist.len());
+        Ok(())
+    }
+
+    // Admin sets which programs recipients may register as release hooks
+    pub fn set_hook_allowlist(ctx: Context<UpdateConfig>, hooks: Vec<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(hooks.len() <= MAX_RECIPIENT_HOOKS, EscrowError::AllowlistFull);
+        config.hook_allowlist = hooks;
+
+        msg!("Hook allowlist set: {} programs", config.hook_allowlist.len());
+        Ok(())
+    }
+
+    // Admin sets which staking programs recipients may have FORGE releases deposited into
+    pub fn set_staking_allowlist(ctx: Context<UpdateConfig>, programs: Vec<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(programs.len() <= MAX_STAKING_PROGRAMS, EscrowError::AllowlistFull);
+        config.staking_allowlist = programs;
+
+        msg!("Staking allowlist set: {} programs", config.staking_allowlist.len());
+        Ok(())
+    }
+
+    // Admin restricts which wallets arbiters may redirect releases to (empty = any wallet)
+    pub fn set_override_allowlist(ctx: Context<UpdateConfig>, wallets: Vec<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(wallets.len() <= MAX_OVERRIDE_RECIPIENTS, EscrowError::AllowlistFull);
+        config.override_allowlist = wallets;
+
+        msg!("Override allowlist set: {} wallets", config.override_allowlist.len());
+        Ok(())
+    }
+
+    // Admin removes a wallet from the initiator allowlist
+    pub fn remove_allowed_initiator(ctx: Context<UpdateConfig>, initiator: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.initiator_allowlist.retain(|allowed| *allowed != initiator);
+
+        msg!("Initiator removed from allowlist: {}", initiator);
+        Ok(())
+    }
+}
+
+impl Escrow {
+    // Returns (net release amount, half fee) for a gross release amount
+    fn split_fee_bps(release_amount_before_fee: u64, fee_bps: u16) -> (u64, u64) {
+        let fee_amount = (release_amount_before_fee as u128 * fee_bps as u128 / 10_000) as u64;
+        let half_fee = fee_amount / 2; // Half each for different purposes
+        // Net is what the two halves leave, so net + 2 * half_fee == gross and no dust is
+        // stranded in the vault by rounding. 2 * half_fee never exceeds the fee, itself at most
+        // the gross, so this cannot underflow.
+        (release_amount_before_fee.saturating_sub(2 * half_fee), half_fee)
+    }
+
+    // Lamports reimbursed to the arbiter for resolving a SOL escrow, capped by what is left
+    fn arbiter_gas_rebate(&self, config: &Config, remaining_amount: u64) -> u64 {
+        match self.deal_type {
+            DealType::Sol => config.arbiter_gas_rebate.min(remaining_amount),
+            DealType::Forge => 0,
+        }
+    }
+
+    // Empty the SOL vault: its rent-exempt reserve goes back to the initiator who paid it and
+    // any dust above that, e.g. lamports sent in outside the program, to the treasury. Returns
+    // (rent, dust). A zero-lamport account is garbage-collected at the end of the transaction.
+    fn drain_sol_vault(
+        escrow_sol_vault: &AccountInfo,
+        initiator: &AccountInfo,
+        treasury: &AccountInfo,
+    ) -> Result<(u64, u64)> {
+        let dust = Escrow::sol_vault_available(escrow_sol_vault)?;
+        let rent = escrow_sol_vault.lamports() - dust;
+        **escrow_sol_vault.try_borrow_mut_lamports()? -= rent + dust;
+        **initiator.try_borrow_mut_lamports()? += rent;
+        **treasury.try_borrow_mut_lamports()? += dust;
+        Ok((rent, dust))
+    }
+
+    // Reads (initiator, status) from the fixed-layout prefix every Escrow account has had since
+    // the first release, so accounts created before later fields were appended, which no
+    // longer deserialize as the current struct, can still be checked
+    fn read_prefix(escrow: &AccountInfo) -> Result<(Pubkey, EscrowStatus)> {
+        require_keys_eq!(*escrow.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        let data = escrow.try_borrow_data()?;
+        require!(data.len() > ESCROW_STATUS_OFFSET, ErrorCode::AccountDidNotDeserialize);
+        require!(data[..8] == <Escrow as anchor_lang::Discriminator>::DISCRIMINATOR, ErrorCode::AccountDiscriminatorMismatch);
+
+        // discriminator + escrow_id
+        let initiator = Pubkey::try_from(&data[16..48]).map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+        let status = EscrowStatus::deserialize(&mut &data[ESCROW_STATUS_OFFSET..])?;
+        Ok((initiator, status))
+    }
+
+    // Shared by close_escrow and can_close: the escrow is finished, the read window after
+    // finalization has passed and no tokens would be stranded in a FORGE vault. SOL dust is
+    // not a blocker since closing sweeps it to the treasury.
+    fn check_closable(
+        &self,
+        config: &Config,
+        escrow_token_vault: &Option<InterfaceAccount<TokenAccount>>,
+        now: i64,
+    ) -> Result<()> {
+        require!(
+            matches!(self.status, EscrowStatus::Cancelled | EscrowStatus::Released),
+            EscrowError::InvalidEscrowStatus
+        );
+        require!(
+            now >= self.finalized_at + config.close_grace_period,
+            EscrowError::GracePeriodActive
+        );
+        if self.deal_type == DealType::Forge {
+            require!(
+                Escrow::deal_account(escrow_token_vault)?.amount == 0,
+                EscrowError::VaultNotEmpty
+            );
+        }
+        Ok(())
+    }
+
+    // Lamports in the SOL vault above its rent-exempt floor
+    fn sol_vault_available(escrow_sol_vault: &AccountInfo) -> Result<u64> {
+        let rent_floor = Rent::get()?.minimum_balance(escrow_sol_vault.data_len());
+        Ok(escrow_sol_vault.lamports().saturating_sub(rent_floor))
+    }
+
+    // Amount in whole units for logs, e.g. 1.500000000 SOL
+    fn format_amount(amount: u64, decimals: u8, label: &str) -> String {
+        if decimals == 0 {
+            return format!("{} {}", amount, label);
+        }
+        let scale = 10u64.pow(decimals as u32);
+        format!(
+            "{}.{:0width$} {}",
+            amount / scale,
+            amount % scale,
+            label,
+            width = decimals as usize
+        )
+    }
+
+    // Deal-specific accounts are optional in the context; fetch one the deal type needs
+    fn deal_account<T>(account: &Option<T>) -> Result<&T> {
+        account.as_ref().ok_or_else(|| error!(EscrowError::MissingDealAccount))
+    }
+
+    fn sol_vault_address(&self) -> Result<Pubkey> {
+        Pubkey::create_program_address(
+            &[
+                b"sol_vault",
+                self.escrow_id.to_le_bytes().as_ref(),
+                &[self.sol_vault_bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| error!(EscrowError::InvalidDealType))
+    }
+
+    // Nothing released yet and still inside the configured window after funding
+    fn in_cooling_off(&self, config: &Config) -> Result<bool> {
+        Ok(config.cooling_off_period > 0 &&
+            self.released_amount == 0 &&
+            Clock::get()?.unix_timestamp < self.funded_at + config.cooling_off_period)
+    }
+
+    // Funds are still held and can be released or cancelled
+    fn is_open(&self) -> bool {
+        matches!(self.status, EscrowStatus::Funded | EscrowStatus::PartiallyReleased)
+    }
+
+    // Two-party escrows store the default key, which nobody can sign as
+    fn has_arbiter(&self) -> bool {
+        self.arbiter != Pubkey::default()
+    }
+
+    // Single source of truth for who may sign a release: the arbiter, if there is one, or
+    // the initiator
+    fn is_authorized_to_release(&self, signer: &Pubkey) -> Result<()> {
+        if (self.has_arbiter() && *signer == self.arbiter) || *signer == self.initiator {
+            return Ok(());
+        }
+        msg!(
+            "Escrow ID {}: {} is neither the arbiter nor the initiator and may not release",
+            self.escrow_id,
+            signer
+        );
+        err!(EscrowError::Unauthorized)
+    }
+
+    // A contributor's proportional part of an amount leaving the escrow
+    fn contribution_share(&self, amount: u64, contribution: &Contribution) -> u64 {
+        (amount as u128 * contribution.amount as u128 / self.amount as u128) as u64
+    }
+
+    fn is_fully_released(&self) -> bool {
+        self.released_amount >= self.amount
+    }
+
+    // A release that would leave only dust behind takes the remainder too, finalizing the escrow
+    fn with_dust_swept(&self, config: &Config, release_amount: u64) -> u64 {
+        let remaining_amount = self.amount.saturating_sub(self.released_amount);
+        let left_over = remaining_amount.saturating_sub(release_amount);
+        if left_over > 0 && left_over < config.dust_threshold(&self.deal_type) {
+            remaining_amount
+        } else {
+            release_amount
+        }
+    }
+
+    fn details(&self) -> EscrowDetails {
+        EscrowDetails {
+            escrow_id: self.escrow_id,
+            initiator: self.initiator,
+            recipient: self.recipient,
+            arbiter: self.arbiter,
+            amount: self.amount,
+            released_amount: self.released_amount,
+            deal_type: self.deal_type.clone(),
+            status: self.status.clone(),
+            schema_version: self.schema_version,
+        }
+    }
+
+    // Commitment to the current label and terms, as recorded in metadata_history
+    fn metadata_hash(&self) -> [u8; 32] {
+        hashv(&[self.label.as_bytes(), &self.terms_uri_hash]).to_bytes()
+    }
+
+    // SHA-256 of the Borsh-serialized state, i.e. the account data after its discriminator
+    // up to the unused space reserved for growth
+    fn state_hash(&self) -> Result<[u8; 32]> {
+        Ok(hash(&self.try_to_vec()?).to_bytes())
+    }
+
+    // Gross amount for a percentage of what is still unreleased. The product is taken in u128
+    // so large escrows cannot overflow, and a share that rounds down to nothing is rejected
+    // rather than recorded as an empty release.
+    fn percentage_of_remaining(&self, percentage: u8) -> Result<u64> {
+        let remaining_amount = self
+            .amount
+            .checked_sub(self.released_amount)
+            .ok_or(EscrowError::NoFundsToRelease)?;
+        let share = remaining_amount as u128 * percentage as u128 / 100;
+        let share = u64::try_from(share).map_err(|_| EscrowError::NoFundsToRelease)?;
+        require!(share > 0, EscrowError::NoFundsToRelease);
+        Ok(share)
+    }
+
+    // True while a required acceptance has not been given yet, either explicitly or by
+    // the auto-accept window running out
+    fn awaiting_acceptance(&self) -> Result<bool> {
+        let auto_accepted =
+            self.auto_accept_at != 0 && Clock::get()?.unix_timestamp >= self.auto_accept_at;
+        Ok(self.accept_deadline != 0 && !self.accepted && !auto_accepted)
+    }
+
+    // Current USD price of an asset from a Pyth price account, rejecting stale prices
+    fn load_usd_price(price_feed: &AccountInfo) -> Result<Price> {
+        let feed = SolanaPriceAccount::account_info_to_feed(price_feed)
+            .map_err(|_| error!(EscrowError::InvalidPriceFeed))?;
+        let now = Clock::get()?.unix_timestamp;
+        let price = feed
+            .get_price_no_older_than(now, MAX_PRICE_AGE_SECS)
+            .ok_or(EscrowError::StalePrice)?;
+        require!(price.price > 0, EscrowError::InvalidPriceFeed);
+        Ok(price)
+    }
+
+    // Base units (lamports, or token units with `decimals`) worth `usd_amount` (6 decimals)
+    // at a price of `price * 10^expo` USD per whole unit
+    fn usd_to_base_units(usd_amount: u64, price: &Price, decimals: u8) -> Result<u64> {
+        // base units = usd / 10^6 / (price * 10^expo) * 10^decimals
+        let mut numerator = usd_amount as u128 * 10u128.pow(decimals as u32);
+        let mut denominator = price.price as u128 * 1_000_000;
+        if price.expo < 0 {
+            numerator *= 10u128.pow(price.expo.unsigned_abs());
+        } else {
+            denominator *= 10u128.pow(price.expo as u32);
+        }
+        u64::try_from(numerator / denominator).map_err(|_| error!(EscrowError::InvalidPriceFeed))
+    }
+
+    // Carve the referrer's cut out of the platform fee. Returns (referrer fee, reduced half fee)
+    fn referrer_fee_split(&self, half_fee: u64) -> (u64, u64) {
+        if self.referrer.is_none() {
+            return (0, half_fee);
+        }
+        let fee = 2 * half_fee;
+        let referrer_fee = (fee as u128 * self.referrer_bps as u128 / 10_000) as u64;
+        let remaining_half = (fee - referrer_fee) / 2;
+        (fee - 2 * remaining_half, remaining_half)
+    }
+
+    // Returns (net release amount, half fee) at the standard rate, clamped to the governance
+    // cap, with no fee when it was already taken at funding
+    fn release_fee_split(&self, config: &Config, release_amount_before_fee: u64) -> (u64, u64) {
+        self.release_fee_split_bps(release_amount_before_fee, config.capped_fee_bps(DEFAULT_FEE_BPS))
+    }
+
+    fn release_fee_split_bps(&self, release_amount_before_fee: u64, fee_bps: u16) -> (u64, u64) {
+        if self.fee_at_funding {
+            return (release_amount_before_fee, 0);
+        }
+        let (_, half_fee) = Escrow::split_fee_bps(release_amount_before_fee, fee_bps);
+        // Anything over the cap stays with the recipient
+        let half_fee = if self.fee_cap_absolute == 0 {
+            half_fee
+        } else {
+            half_fee.min(self.fee_cap_absolute / 2)
+        };
+        (release_amount_before_fee - 2 * half_fee, half_fee)
+    }
+
+    // Close out a milestone escrow once no milestone is left pending
+    fn settle_milestones(&mut self) -> Result<()> {
+        if self
+            .milestones
+            .iter()
+            .any(|m| matches!(m.status, MilestoneStatus::Pending | MilestoneStatus::Disputed))
+        {
+            if self.released_amount > 0 {
+                self.status = EscrowStatus::PartiallyReleased;
+            }
+            return Ok(());
+        }
+        self.status = if self.released_amount > 0 {
+            EscrowStatus::Released
+        } else {
+            EscrowStatus::Cancelled
+        };
+        self.stamp_finalized()
+    }
+
+    // In require-both mode every payout needs the arbiter's signature and a passed release time
+    fn check_require_both(&self, signer: &Pubkey) -> Result<()> {
+        if !self.require_both {
+            return Ok(());
+        }
+        require_keys_eq!(*signer, self.arbiter, EscrowError::ArbiterApprovalRequired);
+        require!(
+            self.release_at != 0 && Clock::get()?.unix_timestamp >= self.release_at,
+            EscrowError::ReleaseNotDue
+        );
+        Ok(())
+    }
+
+    // Split evenly between the treasury and the burn; `mint` is None for SOL
+    fn emit_fee_collected(&self, half_fee: u64) {
+        emit!(FeeCollected {
+            version: EVENT_SCHEMA_VERSION,
+            escrow_id: self.escrow_id,
+            treasury_amount: half_fee,
+            burn_amount: half_fee,
+            mint: match self.deal_type {
+                DealType::Sol => None,
+                DealType::Forge => Some(self.mint),
+            },
+        });
+    }
+
+    // Record when the escrow first reached Released or Cancelled
+    fn stamp_finalized(&mut self) -> Result<()> {
+        let terminal = matches!(self.status, EscrowStatus::Released | EscrowStatus::Cancelled);
+        if terminal && self.finalized_at == 0 {
+            self.finalized_at = Clock::get()?.unix_timestamp;
+        }
+        Ok(())
+    }
+
+    // Plain transfer through the escrow's token program. Token-2022 deprecates it in favour
+    // of transfer_checked, but it still works for mints without transfer-fee or hook extensions.
+    #[allow(deprecated)]
+    fn token_transfer<'info>(
+        ctx: CpiContext<'_, '_, '_, 'info, Transfer<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        token_interface::transfer(ctx, amount)
+    }
+
+    // Any accounts left over on a FORGE transfer through an alternate token program are the
+    // extra accounts the mint's transfer hook needs, resolved off-chain from its
+    // extra-account-metas PDA (validation account, hook program and whatever it lists)
+    fn transfer_hook<'a, 'info>(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        extra_accounts: &'a [AccountInfo<'info>],
+    ) -> Option<TransferHook<'a, 'info>> {
+        if extra_accounts.is_empty()
+            || self.deal_type != DealType::Forge
+            || self.token_program_id() == token::ID
+        {
+            return None;
+        }
+        Some(TransferHook {
+            mint: mint.to_account_info(),
+            decimals: mint.decimals,
+            extra_accounts,
+        })
+    }
+
+    // Move tokens into or out of a vault. A mint with a transfer hook needs transfer_checked with the
+    // hook's extra accounts appended, which the token_interface wrapper does not forward.
+    fn vault_transfer<'info>(
+        token_program: AccountInfo<'info>,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        signer: &[&[&[u8]]],
+        amount: u64,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<()> {
+        let Some(transfer_hook) = transfer_hook else {
+            let transfer_ctx = CpiContext::new_with_signer(
+                token_program,
+                Transfer { from, to, authority },
+                signer,
+            );
+            return Escrow::token_transfer(transfer_ctx, amount);
+        };
+
+        let mut ix = spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            from.key,
+            transfer_hook.mint.key,
+            to.key,
+            authority.key,
+            &[],
+            amount,
+            transfer_hook.decimals,
+        )?;
+        ix.accounts.extend(transfer_hook.extra_accounts.iter().map(|account| AccountMeta {
+            pubkey: account.key(),
+            is_signer: false,
+            is_writable: account.is_writable,
+        }));
+
+        let mut account_infos = vec![from, transfer_hook.mint.clone(), to, authority, token_program];
+        account_infos.extend_from_slice(transfer_hook.extra_accounts);
+        invoke_signed(&ix, &account_infos, signer)?;
+        Ok(())
+    }
+
+    // SPL Token unless the escrow was created with an allowlisted alternate token program
+    fn token_program_id(&self) -> Pubkey {
+        if self.token_program == Pubkey::default() {
+            token::ID
+        } else {
+            self.token_program
+        }
+    }
+
+    // Defense-in-depth against program substitution before any CPI
+    fn assert_program(program: &AccountInfo, expected: &Pubkey) -> Result<()> {
+        require!(program.executable, EscrowError::InvalidProgram);
+        require_keys_eq!(program.key(), *expected, EscrowError::InvalidProgram);
+        Ok(())
+    }
+
+    fn handle_sol_release(
+        escrow_sol_vault: AccountInfo,
+        recipient: AccountInfo,
+        fee_wallet: AccountInfo,
+        temp_fee_wallet: AccountInfo,
+        release_amount: u64,
+        half_fee: u64,
+    ) -> Result<()> {
+        // Send release amount to recipient
+        **escrow_sol_vault.try_borrow_mut_lamports()? -= release_amount;
+        **recipient.try_borrow_mut_lamports()? += release_amount;
+        
+        // Transfer 5% fee to fee wallet
+        **escrow_sol_vault.try_borrow_mut_lamports()? -= half_fee;
+        **fee_wallet.try_borrow_mut_lamports()? += half_fee;
+        
+        // TODO: Buy Forge token from dex by 5% of fee and burn it
+        // Note: In production, implement DEX swap for remaining 5%
+        // For now, sending remaining fee to temp fee wallet
+        **escrow_sol_vault.try_borrow_mut_lamports()? -= half_fee;
+        **temp_fee_wallet.try_borrow_mut_lamports()? += half_fee;
+        
+        Ok(())
+    }
+    
+    fn handle_forge_release<'info>(
+        escrow_token_vault: AccountInfo<'info>,
+        recipient_token_account: AccountInfo<'info>,
+        fee_wallet_token_account: AccountInfo<'info>,
+        burn_token_account: AccountInfo<'info>, 
+        forge_mint: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        escrow_authority: AccountInfo<'info>,
+        release_amount: u64,
+        half_fee: u64,
+        bump: u8,
+        escrow_id: u64,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<()> {
+        require!(token_program.executable, EscrowError::InvalidProgram);
+
+        let escrow_id_bytes = escrow_id.to_le_bytes();
+        let seeds = &[
+            b"escrow",
+            escrow_id_bytes.as_ref(),
+            &[bump]
+        ];
+        let signer = &[&seeds[..]];
+        
+        // Send release amount to recipient (skipped when it is staked on their behalf)
+        if release_amount > 0 {
+            Escrow::vault_transfer(
+                token_program.clone(),
+                escrow_token_vault.clone(),
+                recipient_token_account,
+                escrow_authority.clone(),
+                signer,
+                release_amount,
+                transfer_hook,
+            )?;
+        }
+        
+        // Transfer 5% fee to fee wallet
+        Escrow::vault_transfer(
+            token_program.clone(),
+            escrow_token_vault.clone(),
+            fee_wallet_token_account,
+            escrow_authority.clone(),
+            signer,
+            half_fee,
+            transfer_hook,
+        )?;
+        
+        // Burn 5% of tokens
+        Escrow::vault_transfer(
+            token_program,
+            escrow_token_vault,
+            burn_token_account,
+            escrow_authority,
+            signer,
+            half_fee,
+            transfer_hook,
+        )?;
+        
+        Ok(())
+    }
+
+    fn handle_forge_refund<'info>(
+        escrow_token_vault: AccountInfo<'info>,
+        destination: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        escrow_authority: AccountInfo<'info>,
+        amount: u64,
+        bump: u8,
+        escrow_id: u64,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<()> {
+        require!(token_program.executable, EscrowError::InvalidProgram);
+
+        let escrow_id_bytes = escrow_id.to_le_bytes();
+        let seeds = &[
+            b"escrow",
+            escrow_id_bytes.as_ref(),
+            &[bump]
+        ];
+        let signer = &[&seeds[..]];
+        
+        Escrow::vault_transfer(
+            token_program,
+            escrow_token_vault,
+            destination,
+            escrow_authority,
+            signer,
+            amount,
+            transfer_hook,
+        )
+    }
+
+    // Take the referrer's cut off a fee before the treasury/burn split, paid out of the vault
+    // as lamports to their wallet (SOL) or tokens to their token account (FORGE). Returns what
+    // is left of each half.
+    fn pay_referrer_cut<'info>(
+        escrow: &Account<'info, Escrow>,
+        half_fee: u64,
+        vault: AccountInfo<'info>,
+        referrer: Option<AccountInfo<'info>>,
+        token_program: AccountInfo<'info>,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<u64> {
+        let (referrer_fee, half_fee) = escrow.referrer_fee_split(half_fee);
+        if referrer_fee == 0 {
+            return Ok(half_fee);
+        }
+        let referrer = referrer.ok_or(EscrowError::MissingReferrerAccount)?;
+        match escrow.deal_type {
+            DealType::Sol => {
+                **vault.try_borrow_mut_lamports()? -= referrer_fee;
+                **referrer.try_borrow_mut_lamports()? += referrer_fee;
+            },
+            DealType::Forge => Escrow::handle_forge_refund(
+                vault,
+                referrer,
+                token_program,
+                escrow.to_account_info(),
+                referrer_fee,
+                escrow.bump,
+                escrow.escrow_id,
+                transfer_hook,
+            )?,
+        }
+        Ok(half_fee)
+    }
+
+    // Route the insurance slice of a SOL fee from the vault into the pool, returning what is
+    // left of each half. FORGE fees are not insured.
+    fn pay_insurance_cut<'info>(
+        escrow: &Escrow,
+        config: &Config,
+        half_fee: u64,
+        escrow_sol_vault: AccountInfo<'info>,
+        insurance_pool: Option<AccountInfo<'info>>,
+    ) -> Result<u64> {
+        if escrow.deal_type != DealType::Sol {
+            return Ok(half_fee);
+        }
+        let (insurance_fee, half_fee) = config.insurance_fee_split(half_fee);
+        if insurance_fee > 0 {
+            let insurance_pool = insurance_pool.ok_or(EscrowError::MissingInsurancePool)?;
+            **escrow_sol_vault.try_borrow_mut_lamports()? -= insurance_fee;
+            **insurance_pool.try_borrow_mut_lamports()? += insurance_fee;
+        }
+        Ok(half_fee)
+    }
+}
+
+impl<'info> InitializeEscrow<'info> {
+    // The token owner when a delegate deposits on their behalf, otherwise the signer
+    fn escrow_initiator(&self) -> Pubkey {
+        self.token_owner.as_ref().map_or(self.initiator.key(), |owner| owner.key())
+    }
+
+    // An enabled whitelist only lets the initiator's escrows pay approved recipients
+    fn check_recipient_whitelist(&self, recipient: &Pubkey) -> Result<()> {
+        RecipientWhitelist::check(&self.recipient_whitelist, recipient)
+    }
+
+    // A delegated deposit must come from the owner's token account, approved for the
+    // signer to move at least the full amount (the funding fee included)
+    fn validate_delegate(&self, deal_type: &DealType, amount: u64) -> Result<()> {
+        let Some(owner) = self.token_owner.as_ref() else {
+            return Ok(());
+        };
+        require!(*deal_type == DealType::Forge, EscrowError::InvalidDealType);
+        let source = &self.initiator_token_account;
+        require_keys_eq!(source.owner, owner.key(), EscrowError::InvalidDelegate);
+        require!(
+            source.delegate == COption::Some(self.initiator.key()),
+            EscrowError::InvalidDelegate
+        );
+        require!(source.delegated_amount >= amount, EscrowError::InsufficientDelegation);
+        Ok(())
+    }
+
+    // Count the deposit against the initiator's window, starting a new window once the old one ends
+    fn record_deposit(&mut self, deal_type: &DealType, amount: u64, now: i64) -> Result<()> {
+        UserStats::record_deposit(self.user_stats.as_deref_mut(), &self.config, deal_type, amount, now)
+    }
+
+    // Route the platform fee straight from the initiator when it is charged at funding
+    fn collect_funding_fee(&self, half_fee: u64) -> Result<()> {
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                let fee_wallet = self.fee_wallet.as_ref().ok_or(EscrowError::MissingFeeAccount)?;
+                let temp_fee_wallet = self.temp_fee_wallet.as_ref().ok_or(EscrowError::MissingFeeAccount)?;
+
+                // 5% to the fee wallet, 5% set aside for buy-and-burn
+                for destination in [fee_wallet.to_account_info(), temp_fee_wallet.to_account_info()] {
+                    let transfer_instruction = system_instruction::transfer(
+                        &self.initiator.key(),
+                        &destination.key(),
+                        half_fee,
+                    );
+                    invoke(
+                        &transfer_instruction,
+                        &[
+                            self.initiator.to_account_info(),
+                            destination,
+                            self.system_program.to_account_info(),
+                        ],
+                    )?;
+                }
+            },
+            DealType::Forge => {
+                let fee_wallet_token_account = self
+                    .fee_wallet_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingFeeAccount)?;
+                let burn_token_account = self
+                    .burn_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingFeeAccount)?;
+                require_keys_neq!(
+                    fee_wallet_token_account.key(),
+                    burn_token_account.key(),
+                    EscrowError::InvalidFeeWallet
+                );
+
+                // 5% to the fee wallet, 5% burned
+                for destination in [
+                    fee_wallet_token_account.to_account_info(),
+                    burn_token_account.to_account_info(),
+                ] {
+                    let transfer_ctx = CpiContext::new(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: self.initiator_token_account.to_account_info(),
+                            to: destination,
+                            authority: self.initiator.to_account_info(),
+                        },
+                    );
+                    Escrow::token_transfer(transfer_ctx, half_fee)?;
+                }
+            }
+        }
+
+        self.escrow.emit_fee_collected(half_fee);
+        Ok(())
+    }
+}
+
+impl<'info> ReleaseFunds<'info> {
+    // Returns the half fee actually paid to each of the treasury and burn, after any referrer cut
+    fn transfer_release(
+        &self,
+        net_release_amount: u64,
+        half_fee: u64,
+        override_recipient: Option<Pubkey>, // Already checked by validate_override_recipient
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        self.validate_deal_accounts()?;
+        // The caller picks the payout accounts, so without an override they must be the recipient's
+        if override_recipient.is_none() {
+            require_keys_eq!(self.destination()?, self.escrow.recipient, EscrowError::Unauthorized);
+        }
+        // Never take a fee on a release that delivers nothing to the recipient
+        require!(net_release_amount > 0, EscrowError::NoFundsToRelease);
+        self.ensure_vault_covers(net_release_amount + 2 * half_fee)?;
+
+        let transfer_hook = self.transfer_hook(remaining_accounts)?;
+
+        let half_fee = self.pay_fee_cuts(half_fee, transfer_hook.as_ref())?;
+        self.emit_fee_collected(half_fee);
+
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                // The buy-and-burn half waits in the accumulator, when passed, for a keeper
+                // to swap and burn in bulk
+                let burn_destination = match &self.pending_burn {
+                    Some(pending_burn) => pending_burn.to_account_info(),
+                    None => Escrow::deal_account(&self.temp_fee_wallet)?.to_account_info(),
+                };
+
+                // Handle SOL payment
+                Escrow::handle_sol_release(
+                    Escrow::deal_account(&self.escrow_sol_vault)?.to_account_info(),
+                    Escrow::deal_account(&self.recipient)?.to_account_info(),
+                    Escrow::deal_account(&self.fee_wallet)?.to_account_info(),
+                    burn_destination,
+                    net_release_amount,
+                    half_fee,
+                )?;
+            },
+            DealType::Forge => {
+                let staking_program = self.escrow.auto_stake_program;
+
+                // Handle FORGE token payment
+                Escrow::handle_forge_release(
+                    Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                    Escrow::deal_account(&self.recipient_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.fee_wallet_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.burn_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.forge_mint)?.to_account_info(),
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    if staking_program.is_some() { 0 } else { net_release_amount },
+                    half_fee,
+                    self.escrow.bump,
+                    self.escrow.escrow_id,
+                    transfer_hook.as_ref(),
+                )?;
+
+                if let Some(staking_program) = staking_program {
+                    self.stake_for_recipient(staking_program, remaining_accounts, net_release_amount)?;
+                }
+            }
+        }
+        Ok(half_fee)
+    }
+
+    // Gates every release path applies before paying out: the external release condition and
+    // require-both mode. The arbiter's settlement paths apply require-both only, since a
+    // dispute is exactly when the condition may never be met.
+    fn check_payout_gate(&self) -> Result<()> {
+        if let Some(condition) = &self.escrow.release_condition {
+            self.check_release_condition(condition)?;
+        }
+        self.escrow.check_require_both(&self.signer.key())
+    }
+
+    // The referenced account must hold the expected byte at the given offset
+    fn check_release_condition(&self, condition: &ReleaseCondition) -> Result<()> {
+        let condition_account = self
+            .condition_account
+            .as_ref()
+            .ok_or(EscrowError::MissingConditionAccount)?;
+        require_keys_eq!(
+            condition_account.key(),
+            condition.account,
+            EscrowError::InvalidConditionAccount
+        );
+
+        let data = condition_account.try_borrow_data()?;
+        require!(
+            data.get(condition.offset as usize) == Some(&condition.expected),
+            EscrowError::ReleaseConditionNotMet
+        );
+        Ok(())
+    }
+
+    // The referrer's cut and then the insurance slice come off every fee before the
+    // treasury/burn split. Returns what is left of each half.
+    fn pay_fee_cuts(&self, half_fee: u64, transfer_hook: Option<&TransferHook<'_, 'info>>) -> Result<u64> {
+        let half_fee = self.pay_referrer(half_fee, transfer_hook)?;
+        if self.escrow.deal_type != DealType::Sol {
+            return Ok(half_fee);
+        }
+        Escrow::pay_insurance_cut(
+            &self.escrow,
+            &self.config,
+            half_fee,
+            Escrow::deal_account(&self.escrow_sol_vault)?.to_account_info(),
+            self.insurance_pool.as_ref().map(|pool| pool.to_account_info()),
+        )
+    }
+
+    // Only the arbiter may redirect a release, and only to the passed-in destination
+    fn validate_override_recipient(&self, override_recipient: Pubkey) -> Result<()> {
+        require_keys_eq!(self.signer.key(), self.escrow.arbiter, EscrowError::Unauthorized);
+
+        let allowlist = &self.config.override_allowlist;
+        require!(
+            allowlist.is_empty() || allowlist.contains(&override_recipient),
+            EscrowError::InvalidOverrideRecipient
+        );
+
+        require_keys_eq!(self.destination()?, override_recipient, EscrowError::InvalidOverrideRecipient);
+        Ok(())
+    }
+
+    // Wallet the payout accounts passed in belong to
+    fn destination(&self) -> Result<Pubkey> {
+        Ok(match self.escrow.deal_type {
+            DealType::Sol => Escrow::deal_account(&self.recipient)?.key(),
+            DealType::Forge => Escrow::deal_account(&self.recipient_token_account)?.owner,
+        })
+    }
+
+    // Lowest fee among the tiers the initiator's volume qualifies for; the standard fee
+    // applies when no user stats account is passed. Either way clamped to the governance cap.
+    fn initiator_fee_bps(&self) -> u16 {
+        let Some(user_stats) = self.user_stats.as_ref() else {
+            return self.config.capped_fee_bps(DEFAULT_FEE_BPS);
+        };
+        let volume = user_stats.volume(&self.escrow.deal_type);
+        let fee_bps = self
+            .config
+            .fee_tiers
+            .iter()
+            .filter(|tier| tier.deal_type == self.escrow.deal_type && volume >= tier.min_volume)
+            .map(|tier| tier.fee_bps)
+            .fold(DEFAULT_FEE_BPS, u16::min);
+        self.config.capped_fee_bps(fee_bps)
+    }
+
+    fn record_volume(&mut self, amount: u64) {
+        let deal_type = self.escrow.deal_type.clone();
+        if let Some(user_stats) = self.user_stats.as_mut() {
+            match deal_type {
+                DealType::Sol => user_stats.sol_volume += amount,
+                DealType::Forge => user_stats.forge_volume += amount,
+            }
+        }
+    }
+
+    // With neither auto-stake nor a recipient hook consuming them, remaining_accounts on a
+    // FORGE release are the mint's transfer-hook accounts
+    fn transfer_hook<'a>(
+        &self,
+        remaining_accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Option<TransferHook<'a, 'info>>> {
+        if self.escrow.deal_type != DealType::Forge
+            || self.escrow.auto_stake_program.is_some()
+            || self.escrow.recipient_hook.is_some()
+        {
+            return Ok(None);
+        }
+        let forge_mint = Escrow::deal_account(&self.forge_mint)?;
+        Ok(self.escrow.transfer_hook(forge_mint, remaining_accounts))
+    }
+
+    // The referrer's cut comes off the fee before the treasury/burn split
+    fn pay_referrer(&self, half_fee: u64, transfer_hook: Option<&TransferHook<'_, 'info>>) -> Result<u64> {
+        let (vault, referrer) = match self.escrow.deal_type {
+            DealType::Sol => (
+                Escrow::deal_account(&self.escrow_sol_vault)?.to_account_info(),
+                self.referrer.as_ref().map(|referrer| referrer.to_account_info()),
+            ),
+            DealType::Forge => (
+                Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                self.referrer_token_account.as_ref().map(|account| account.to_account_info()),
+            ),
+        };
+        Escrow::pay_referrer_cut(
+            &self.escrow,
+            half_fee,
+            vault,
+            referrer,
+            self.token_program.to_account_info(),
+            transfer_hook,
+        )
+    }
+
+    // Same fee split as a release, but paid back to the initiator and never auto-staked
+    fn transfer_expiry_refund(&self, net_refund_amount: u64, half_fee: u64) -> Result<()> {
+        self.validate_deal_accounts()?;
+        self.ensure_vault_covers(net_refund_amount + 2 * half_fee)?;
+        let half_fee = self.pay_fee_cuts(half_fee, None)?;
+
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                require_keys_eq!(
+                    Escrow::deal_account(&self.recipient)?.key(),
+                    self.escrow.initiator,
+                    EscrowError::Unauthorized
+                );
+                Escrow::handle_sol_release(
+                    Escrow::deal_account(&self.escrow_sol_vault)?.to_account_info(),
+                    Escrow::deal_account(&self.recipient)?.to_account_info(),
+                    Escrow::deal_account(&self.fee_wallet)?.to_account_info(),
+                    Escrow::deal_account(&self.temp_fee_wallet)?.to_account_info(),
+                    net_refund_amount,
+                    half_fee,
+                )?;
+            },
+            DealType::Forge => {
+                require_keys_eq!(
+                    Escrow::deal_account(&self.recipient_token_account)?.owner,
+                    self.escrow.initiator,
+                    EscrowError::Unauthorized
+                );
+                Escrow::handle_forge_release(
+                    Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                    Escrow::deal_account(&self.recipient_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.fee_wallet_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.burn_token_account)?.to_account_info(),
+                    Escrow::deal_account(&self.forge_mint)?.to_account_info(),
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    net_refund_amount,
+                    half_fee,
+                    self.escrow.bump,
+                    self.escrow.escrow_id,
+                    None,
+                )?;
+            }
+        }
+
+        if half_fee > 0 {
+            self.emit_fee_collected(half_fee);
+        }
+        Ok(())
+    }
+
+    // The vault passed in must be the one that belongs to this escrow's deal type, and the
+    // other deal type's accounts must be left out rather than silently ignored
+    fn validate_deal_accounts(&self) -> Result<()> {
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                require!(
+                    self.escrow_token_vault.is_none() &&
+                    self.recipient_token_account.is_none() &&
+                    self.fee_wallet_token_account.is_none() &&
+                    self.burn_token_account.is_none() &&
+                    self.forge_mint.is_none(),
+                    EscrowError::InvalidDealType
+                );
+                require_keys_eq!(
+                    Escrow::deal_account(&self.escrow_sol_vault)?.key(),
+                    self.escrow.sol_vault_address()?,
+                    EscrowError::InvalidDealType
+                );
+            },
+            DealType::Forge => {
+                require!(
+                    self.escrow_sol_vault.is_none() &&
+                    self.recipient.is_none() &&
+                    self.fee_wallet.is_none() &&
+                    self.temp_fee_wallet.is_none(),
+                    EscrowError::InvalidDealType
+                );
+                require_keys_eq!(
+                    Escrow::deal_account(&self.escrow_token_vault)?.owner,
+                    self.escrow.key(),
+                    EscrowError::InvalidDealType
+                );
+                require_keys_neq!(
+                    Escrow::deal_account(&self.burn_token_account)?.key(),
+                    Escrow::deal_account(&self.fee_wallet_token_account)?.key(),
+                    EscrowError::InvalidFeeWallet
+                );
+            },
+        }
+        Ok(())
+    }
+
+    // Fail early with a clear error instead of draining the SOL vault below its rent floor
+    // or relying on the token program
+    fn ensure_vault_covers(&self, amount: u64) -> Result<()> {
+        let available = match self.escrow.deal_type {
+            DealType::Sol => Escrow::sol_vault_available(Escrow::deal_account(&self.escrow_sol_vault)?)?,
+            DealType::Forge => Escrow::deal_account(&self.escrow_token_vault)?.amount,
+        };
+        require!(available >= amount, EscrowError::InsufficientFunds);
+        Ok(())
+    }
+
+    // Invariant: the vault holds everything still unreleased. `amount` is net of any fee
+    // taken at funding, so that fee is never charged against the vault a second time.
+    fn ensure_vault_backs_remaining(&self) -> Result<()> {
+        self.ensure_vault_covers(self.escrow.amount.saturating_sub(self.escrow.released_amount))
+    }
+
+    fn emit_fee_collected(&self, half_fee: u64) {
+        self.escrow.emit_fee_collected(half_fee);
+
+        // Same breakdown in readable units for anyone reading the transaction logs
+        let (decimals, label) = match self.escrow.deal_type {
+            DealType::Sol => (SOL_DECIMALS, "SOL"),
+            DealType::Forge => match &self.forge_mint {
+                Some(mint) => (mint.decimals, "FORGE"),
+                None => (0, "FORGE base units"),
+            },
+        };
+        let half_fee = Escrow::format_amount(half_fee, decimals, label);
+        msg!(
+            "Fee breakdown for escrow ID {} ({:?}): treasury {}, burn {}",
+            self.escrow.escrow_id,
+            self.escrow.deal_type,
+            half_fee,
+            half_fee
+        );
+    }
+
+    // Deposit the recipient's portion into their chosen staking program.
+    // remaining_accounts: [staking program, stake vault, ...extra accounts the program needs]
+    fn stake_for_recipient(
+        &self,
+        staking_program: Pubkey,
+        remaining_accounts: &[AccountInfo<'info>],
+        amount: u64,
+    ) -> Result<()> {
+        require!(remaining_accounts.len() >= 2, EscrowError::InvalidStakingProgram);
+        let program_info = &remaining_accounts[0];
+        let stake_vault = &remaining_accounts[1];
+        require_keys_eq!(program_info.key(), staking_program, EscrowError::InvalidStakingProgram);
+        // Re-checked here in case the program was delisted after the recipient opted in
+        require!(
+            self.config.staking_allowlist.contains(&staking_program),
+            EscrowError::InvalidStakingProgram
+        );
+        Escrow::assert_program(&self.token_program.to_account_info(), &self.escrow.token_program_id())?;
+
+        // deposit_for(amount: u64, beneficiary: Pubkey)
+        let mut data = hash(b"global:deposit_for").to_bytes()[..8].to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(self.escrow.recipient.as_ref());
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.escrow.key(), true),
+            AccountMeta::new(Escrow::deal_account(&self.escrow_token_vault)?.key(), false),
+            AccountMeta::new(stake_vault.key(), false),
+            AccountMeta::new_readonly(self.token_program.key(), false),
+        ];
+        let mut account_infos = vec![
+            self.escrow.to_account_info(),
+            Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+            stake_vault.clone(),
+            self.token_program.to_account_info(),
+        ];
+        for extra in &remaining_accounts[2..] {
+            accounts.push(if extra.is_writable {
+                AccountMeta::new(extra.key(), extra.is_signer)
+            } else {
+                AccountMeta::new_readonly(extra.key(), extra.is_signer)
+            });
+            account_infos.push(extra.clone());
+        }
+        account_infos.push(program_info.clone());
+
+        let escrow_id_bytes = self.escrow.escrow_id.to_le_bytes();
+        let seeds = &[
+            b"escrow",
+            escrow_id_bytes.as_ref(),
+            &[self.escrow.bump]
+        ];
+
+        invoke_signed(
+            &Instruction {
+                program_id: staking_program,
+                accounts,
+                data,
+            },
+            &account_infos,
+            &[&seeds[..]],
+        )?;
+
+        msg!("Staked {} for recipient {}", amount, self.escrow.recipient);
+        Ok(())
+    }
+
+    // Notify the recipient's hook program of a payout. The escrow is passed read-only and
+    // never signs, since its PDA is the token vault's authority.
+    // remaining_accounts: [hook program, ...extra accounts the hook needs]
+    fn call_recipient_hook(
+        &self,
+        hook: Pubkey,
+        remaining_accounts: &[AccountInfo<'info>],
+        amount: u64,
+    ) -> Result<()> {
+        require!(
+            self.config.hook_allowlist.contains(&hook),
+            EscrowError::InvalidRecipientHook
+        );
+        let program_info = remaining_accounts.first().ok_or(EscrowError::InvalidRecipientHook)?;
+        require_keys_eq!(program_info.key(), hook, EscrowError::InvalidRecipientHook);
+        require!(program_info.executable, EscrowError::InvalidProgram);
+
+        let recipient = match self.escrow.deal_type {
+            DealType::Sol => Escrow::deal_account(&self.recipient)?.to_account_info(),
+            DealType::Forge => Escrow::deal_account(&self.recipient_token_account)?.to_account_info(),
+        };
+
+        // on_escrow_release(escrow_id: u64, amount: u64)
+        let mut data = hash(b"global:on_escrow_release").to_bytes()[..8].to_vec();
+        data.extend_from_slice(&self.escrow.escrow_id.to_le_bytes());
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.escrow.key(), false),
+            AccountMeta::new(recipient.key(), false),
+        ];
+        let mut account_infos = vec![self.escrow.to_account_info(), recipient];
+        for extra in &remaining_accounts[1..] {
+            accounts.push(if extra.is_writable {
+                AccountMeta::new(extra.key(), extra.is_signer)
+            } else {
+                AccountMeta::new_readonly(extra.key(), extra.is_signer)
+            });
+            account_infos.push(extra.clone());
+        }
+        account_infos.push(program_info.clone());
+
+        invoke(
+            &Instruction {
+                program_id: hook,
+                accounts,
+                data,
+            },
+            &account_infos,
+        )?;
+
+        msg!("Recipient hook {} notified of {}", hook, amount);
+        Ok(())
+    }
+}
+
+impl<'info> ReleaseFunds<'info> {
+    fn transfer_split(
+        &self,
+        net_release_amount: u64,
+        half_fee: u64,
+        payees: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        self.validate_deal_accounts()?;
+        require!(net_release_amount > 0, EscrowError::NoFundsToRelease);
+        self.ensure_vault_covers(net_release_amount + 2 * half_fee)?;
+        let half_fee = self.pay_fee_cuts(half_fee, None)?;
+        self.emit_fee_collected(half_fee);
+
+        // Fees first, with nothing going to the single recipient
+        match self.escrow.deal_type {
+            DealType::Sol => Escrow::handle_sol_release(
+                Escrow::deal_account(&self.escrow_sol_vault)?.to_account_info(),
+                Escrow::deal_account(&self.recipient)?.to_account_info(),
+                Escrow::deal_account(&self.fee_wallet)?.to_account_info(),
+                Escrow::deal_account(&self.temp_fee_wallet)?.to_account_info(),
+                0,
+                half_fee,
+            )?,
+            DealType::Forge => Escrow::handle_forge_release(
+                Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                Escrow::deal_account(&self.recipient_token_account)?.to_account_info(),
+                Escrow::deal_account(&self.fee_wallet_token_account)?.to_account_info(),
+                Escrow::deal_account(&self.burn_token_account)?.to_account_info(),
+                Escrow::deal_account(&self.forge_mint)?.to_account_info(),
+                self.token_program.to_account_info(),
+                self.escrow.to_account_info(),
+                0,
+                half_fee,
+                self.escrow.bump,
+                self.escrow.escrow_id,
+                None,
+            )?,
+        }
+
+        let escrow_id_bytes = self.escrow.escrow_id.to_le_bytes();
+        let seeds = &[
+            b"escrow",
+            escrow_id_bytes.as_ref(),
+            &[self.escrow.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        let mut paid = 0u64;
+        let last = self.escrow.split_recipients.len() - 1;
+        for (i, (split, payee)) in self.escrow.split_recipients.iter().zip(payees).enumerate() {
+            // Last recipient absorbs rounding dust so the whole net amount goes out
+            let share = if i == last {
+                net_release_amount - paid
+            } else {
+                (net_release_amount as u128 * split.bps as u128 / 10_000) as u64
+            };
+            paid += share;
+
+            match self.escrow.deal_type {
+                DealType::Sol => {
+                    require_keys_eq!(payee.key(), split.wallet, EscrowError::InvalidSplitConfig);
+                    **Escrow::deal_account(&self.escrow_sol_vault)?.try_borrow_mut_lamports()? -= share;
+                    **payee.try_borrow_mut_lamports()? += share;
+                },
+                DealType::Forge => {
+                    let payee_account = InterfaceAccount::<TokenAccount>::try_from(payee)?;
+                    require_keys_eq!(payee_account.owner, split.wallet, EscrowError::InvalidSplitConfig);
+                    require_keys_eq!(payee_account.mint, self.escrow.mint, EscrowError::InvalidSplitConfig);
+
+                    let transfer_ctx = CpiContext::new_with_signer(
+                        self.token_program.to_account_info(),
+                        Transfer {
+                            from: Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                            to: payee.clone(),
+                            authority: self.escrow.to_account_info(),
+                        },
+                        signer,
+                    );
+                    Escrow::token_transfer(transfer_ctx, share)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'info> Settle<'info> {
+    // Pay the recipient their share (less fee), refund the initiator and rebate the arbiter,
+    // emptying the escrow
+    fn pay_out(&mut self, recipient_amount: u64, refund_amount: u64, rebate: u64) -> Result<()> {
+        let (net_release_amount, half_fee) =
+            self.escrow.release_fee_split(&self.config, recipient_amount);
+
+        self.transfer_settlement(net_release_amount, half_fee, refund_amount)?;
+        if rebate > 0 {
+            **self.escrow_sol_vault.try_borrow_mut_lamports()? -= rebate;
+            **self.signer.to_account_info().try_borrow_mut_lamports()? += rebate;
+        }
+        let remaining_amount = recipient_amount + refund_amount + rebate;
+        self.config.unlock_value(&self.escrow.deal_type, remaining_amount);
+
+        self.escrow.released_amount += recipient_amount + rebate;
+        self.escrow.fees_paid += 2 * half_fee;
+        Ok(())
+    }
+
+    fn transfer_settlement(&self, net_release_amount: u64, half_fee: u64, refund_amount: u64) -> Result<()> {
+        let half_fee = self.pay_fee_cuts(half_fee)?;
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                require_keys_eq!(
+                    self.escrow_sol_vault.key(),
+                    self.escrow.sol_vault_address()?,
+                    EscrowError::InvalidDealType
+                );
+                Escrow::handle_sol_release(
+                    self.escrow_sol_vault.to_account_info(),
+                    self.recipient.to_account_info(),
+                    self.fee_wallet.to_account_info(),
+                    self.temp_fee_wallet.to_account_info(),
+                    net_release_amount,
+                    half_fee,
+                )?;
+                **self.escrow_sol_vault.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+                **self.initiator.to_account_info().try_borrow_mut_lamports()? += refund_amount;
+            },
+            DealType::Forge => {
+                require_keys_eq!(
+                    self.escrow_token_vault.owner,
+                    self.escrow.key(),
+                    EscrowError::InvalidDealType
+                );
+                Escrow::handle_forge_release(
+                    self.escrow_token_vault.to_account_info(),
+                    self.recipient_token_account.to_account_info(),
+                    self.fee_wallet_token_account.to_account_info(),
+                    self.burn_token_account.to_account_info(),
+                    self.forge_mint.to_account_info(),
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    net_release_amount,
+                    half_fee,
+                    self.escrow.bump,
+                    self.escrow.escrow_id,
+                    None,
+                )?;
+                if refund_amount > 0 {
+                    Escrow::handle_forge_refund(
+                        self.escrow_token_vault.to_account_info(),
+                        self.initiator_token_account.to_account_info(),
+                        self.token_program.to_account_info(),
+                        self.escrow.to_account_info(),
+                        refund_amount,
+                        self.escrow.bump,
+                        self.escrow.escrow_id,
+                        None,
+                    )?;
+                }
+            }
+        }
+
+        self.escrow.emit_fee_collected(half_fee);
+        Ok(())
+    }
+
+    // Settlements take the referrer's cut and the insurance slice the same way releases do
+    fn pay_fee_cuts(&self, half_fee: u64) -> Result<u64> {
+        let (vault, referrer) = match self.escrow.deal_type {
+            DealType::Sol => (
+                self.escrow_sol_vault.to_account_info(),
+                self.referrer.as_ref().map(|referrer| referrer.to_account_info()),
+            ),
+            DealType::Forge => (
+                self.escrow_token_vault.to_account_info(),
+                self.referrer_token_account.as_ref().map(|account| account.to_account_info()),
+            ),
+        };
+        let half_fee = Escrow::pay_referrer_cut(
+            &self.escrow,
+            half_fee,
+            vault,
+            referrer,
+            self.token_program.to_account_info(),
+            None,
+        )?;
+        Escrow::pay_insurance_cut(
+            &self.escrow,
+            &self.config,
+            half_fee,
+            self.escrow_sol_vault.to_account_info(),
+            self.insurance_pool.as_ref().map(|pool| pool.to_account_info()),
+        )
+    }
+}
+
+impl<'info> CancelEscrow<'info> {
+    // The vault passed in must be the one that belongs to this escrow's deal type, and a SOL
+    // deal's token accounts must be left out
+    fn validate_deal_accounts(&self) -> Result<()> {
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                require!(
+                    self.escrow_token_vault.is_none() &&
+                    self.initiator_token_account.is_none() &&
+                    self.forge_mint.is_none(),
+                    EscrowError::InvalidDealType
+                );
+                require_keys_eq!(
+                    self.escrow_sol_vault.key(),
+                    self.escrow.sol_vault_address()?,
+                    EscrowError::InvalidDealType
+                );
+            },
+            DealType::Forge => require_keys_eq!(
+                Escrow::deal_account(&self.escrow_token_vault)?.owner,
+                self.escrow.key(),
+                EscrowError::InvalidDealType
+            ),
+        }
+        Ok(())
+    }
+
+    fn transfer_hook<'a>(
+        &self,
+        extra_accounts: &'a [AccountInfo<'info>],
+    ) -> Option<TransferHook<'a, 'info>> {
+        let forge_mint = self.forge_mint.as_ref()?;
+        self.escrow.transfer_hook(forge_mint, extra_accounts)
+    }
+
+    fn refund_initiator(
+        &self,
+        amount: u64,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<()> {
+        match self.escrow.deal_type {
+            DealType::Sol => self.refund(self.initiator.to_account_info(), amount, transfer_hook),
+            DealType::Forge => self.refund(
+                Escrow::deal_account(&self.initiator_token_account)?.to_account_info(),
+                amount,
+                transfer_hook,
+            ),
+        }
+    }
+
+    // Refund what is left proportionally to third-party contributors; the initiator gets the rest.
+    // remaining_accounts: one payee per contribution, in order (wallet for SOL, token account for FORGE),
+    // followed by the mint's transfer-hook accounts when it has one
+    fn refund_remaining(
+        &self,
+        remaining_amount: u64,
+        remaining_accounts: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        let contributions = &self.escrow.contributions;
+        require!(remaining_accounts.len() >= contributions.len(), EscrowError::InvalidContributor);
+        let (payees, extra_accounts) = remaining_accounts.split_at(contributions.len());
+        let transfer_hook = self.transfer_hook(extra_accounts);
+        require!(
+            extra_accounts.is_empty() || transfer_hook.is_some(),
+            EscrowError::InvalidContributor
+        );
+
+        let mut refunded = 0u64;
+        for (contribution, payee) in contributions.iter().zip(payees) {
+            match self.escrow.deal_type {
+                DealType::Sol => {
+                    require_keys_eq!(payee.key(), contribution.contributor, EscrowError::InvalidContributor);
+                },
+                DealType::Forge => {
+                    let payee_account = InterfaceAccount::<TokenAccount>::try_from(payee)?;
+                    require_keys_eq!(payee_account.owner, contribution.contributor, EscrowError::InvalidContributor);
+                }
+            }
+
+            let share = self.escrow.contribution_share(remaining_amount, contribution);
+            if share > 0 {
+                self.refund(payee.clone(), share, transfer_hook.as_ref())?;
+            }
+            refunded += share;
+        }
+
+        if remaining_amount > refunded {
+            self.refund_initiator(remaining_amount - refunded, transfer_hook.as_ref())?;
+        }
+        Ok(())
+    }
+
+    // Move funds out of the vault to a wallet (SOL) or token account (FORGE)
+    fn refund(
+        &self,
+        destination: AccountInfo<'info>,
+        amount: u64,
+        transfer_hook: Option<&TransferHook<'_, 'info>>,
+    ) -> Result<()> {
+        self.validate_deal_accounts()?;
+
+        match self.escrow.deal_type {
+            DealType::Sol => {
+                // Return SOL
+                **self.escrow_sol_vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+                **destination.try_borrow_mut_lamports()? += amount;
+                Ok(())
+            },
+            DealType::Forge => {
+                // Return FORGE tokens
+                Escrow::handle_forge_refund(
+                    Escrow::deal_account(&self.escrow_token_vault)?.to_account_info(),
+                    destination,
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    amount,
+                    self.escrow.bump,
+                    self.escrow.escrow_id,
+                    transfer_hook,
+                )
+            }
+        }
+    }
+}
+
+// Account Contexts
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct InitializeEscrow<'info> {
+    #[account(
+        init,
+        payer = initiator,
+        space = 8 + Escrow::INIT_SPACE,
+        seeds = [b"escrow", escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Pays for the accounts and authorizes the token transfer, as owner or approved delegate.
+    // May be a calling program's PDA signing via CPI.
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    // Set when the signer is a relayer depositing as delegate; recorded as the initiator
+    /// CHECK: Must own initiator_token_account, checked in validate_delegate
+    pub token_owner: Option<UncheckedAccount<'info>>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // The initiator's stats; required while a deposit limit is configured
+    #[account(
+        mut,
+        seeds = [
+            b"user_stats",
+            token_owner.as_ref().map_or(initiator.key(), |owner| owner.key()).as_ref()
+        ],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+    
+    // Terms to create the escrow from; initialize_from_template only
+    pub template: Option<Account<'info, Template>>,
+    
+    // The initiator's escrow index; the new escrow is listed in it when passed
+    #[account(
+        mut,
+        seeds = [
+            b"user_index",
+            token_owner.as_ref().map_or(initiator.key(), |owner| owner.key()).as_ref()
+        ],
+        bump = user_index.bump
+    )]
+    pub user_index: Option<Account<'info, UserIndex>>,
+    
+    // Human-friendly code standing in for the referrer's address
+    pub referral_code: Option<Account<'info, ReferralCode>>,
+    
+    // The initiator's recipient whitelist. Always passed, so it cannot be skipped once created;
+    // an address with no account behind it means the initiator has none.
+    /// CHECK: May not exist; deserialized in check_recipient_whitelist
+    #[account(
+        seeds = [
+            b"recipient_whitelist",
+            token_owner.as_ref().map_or(initiator.key(), |owner| owner.key()).as_ref()
+        ],
+        bump
+    )]
+    pub recipient_whitelist: UncheckedAccount<'info>,
+    
+    /// CHECK: This is safe because we're only using it as a vault
+    #[account(
+        init,
+        payer = initiator,
+        space = 0,
+        seeds = [b"sol_vault", escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    // Anyone can create (and fund) the escrow's ATA ahead of time, so a reused
+    // vault must be empty; mint and authority are checked by the ATA constraints
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = forge_mint,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+        constraint = escrow_token_vault.amount == 0 @ EscrowError::VaultNotEmpty
+    )]
+    pub escrow_token_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    // Add initiator's token account for FORGE transfers
+    #[account(mut)]
+    pub initiator_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub forge_mint: InterfaceAccount<'info, Mint>,
+    
+    // Fee destinations, only needed when the fee is charged at funding
+    /// CHECK: Must be the configured treasury
+    #[account(mut, address = config.treasury)]
+    pub fee_wallet: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub temp_fee_wallet: Option<UncheckedAccount<'info>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = forge_mint,
+        associated_token::authority = config.treasury,
+        associated_token::token_program = token_program
+    )]
+    pub fee_wallet_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut, token::mint = forge_mint)]
+    pub burn_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub system_program: Program<'info, System>,
+    // SPL Token, or an alternate token program allowlisted in the config
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFunds<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Arbiter or initiator. The arbiter may be a program PDA (e.g. a DAO governance
+    // authority), in which case the owning program authorizes by signing via CPI.
+    pub signer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // SOL deals only; leave out for FORGE deals
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub escrow_sol_vault: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub recipient: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Must be the configured treasury
+    #[account(mut, address = config.treasury)]
+    pub fee_wallet: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub temp_fee_wallet: Option<UncheckedAccount<'info>>,
+    
+    // FORGE deals only; leave out for SOL deals
+    #[account(mut)]
+    pub escrow_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = forge_mint,
+        associated_token::authority = config.treasury,
+        associated_token::token_program = token_program
+    )]
+    pub fee_wallet_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = burn_token_account.mint == escrow.mint @ EscrowError::InvalidBurnAddress
+    )]
+    pub burn_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// CHECK: Must be the escrow's referrer; required when it has one (SOL deals)
+    #[account(mut, constraint = escrow.referrer == Some(referrer.key()) @ EscrowError::InvalidReferrer)]
+    pub referrer: Option<UncheckedAccount<'info>>,
+    
+    // Required when the escrow has a referrer (FORGE deals)
+    #[account(
+        mut,
+        constraint = escrow.referrer == Some(referrer_token_account.owner) @ EscrowError::InvalidReferrer,
+        token::mint = forge_mint
+    )]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    // The initiator's volume tracker; pass it to apply fee tiers and count this release
+    #[account(mut, seeds = [b"user_stats", escrow.initiator.as_ref()], bump = user_stats.bump)]
+    pub user_stats: Option<Account<'info, UserStats>>,
+    
+    // SOL deals: collects the buy-and-burn half instead of temp_fee_wallet
+    #[account(mut, seeds = [b"pending_burn"], bump = pending_burn.bump)]
+    pub pending_burn: Option<Account<'info, PendingBurn>>,
+    
+    // SOL deals: receives the insurance slice of the fee; required while one is configured
+    #[account(mut, seeds = [b"insurance_pool"], bump = insurance_pool.bump)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    
+    /// CHECK: Only read, and only against the escrow's stored release condition
+    pub condition_account: Option<UncheckedAccount<'info>>,
+    
+    // FORGE deals only
+    #[account(mut)]
+    pub forge_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    pub system_program: Program<'info, System>,
+    #[account(address = escrow.token_program_id() @ EscrowError::InvalidProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetMilestones<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub initiator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptanceDeadline<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub initiator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptEscrow<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // The recipient may be a program PDA (e.g. a multisig vault), in which case the owning
+    // program accepts by signing via CPI once its own approval rules are met
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeMilestone<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Initiator or recipient
+    pub party: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauseEscrow<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Initiator or recipient
+    pub party: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleRelease<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub initiator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReviewRelease<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUsdAmount<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub initiator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct AddLabel<'info> {
+    #[account(
+        mut,
+        has_one = initiator @ EscrowError::Unauthorized,
+        realloc = 8 + Escrow::INIT_SPACE + label.len(),
+        realloc::payer = initiator,
+        realloc::zero = false
+    )]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct UpdateMetadata<'info> {
+    #[account(
+        mut,
+        has_one = initiator @ EscrowError::Unauthorized,
+        has_one = recipient @ EscrowError::Unauthorized,
+        realloc = 8 + Escrow::INIT_SPACE + label.len(),
+        realloc::payer = initiator,
+        realloc::zero = false
+    )]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    pub recipient: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetSplitRecipients<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub initiator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutoStake<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub recipient: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct AssignPayout<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub recipient: Signer<'info>,
+    
+    // The initiator's recipient whitelist, checked against the assignee
+    /// CHECK: May not exist; deserialized in RecipientWhitelist::check
+    #[account(seeds = [b"recipient_whitelist", escrow.initiator.as_ref()], bump)]
+    pub recipient_whitelist: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Contribute<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // The contributor's stats; required while a deposit limit is configured
+    #[account(
+        mut,
+        seeds = [b"user_stats", contributor.key().as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Option<Account<'info, UserStats>>,
+    
+    /// CHECK: Checked against the escrow's SOL vault PDA
+    #[account(mut)]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    // The escrow's own ATA, so contributions can't land in an account of another mint
+    #[account(
+        mut,
+        associated_token::mint = forge_mint,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub contributor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        constraint = escrow.deal_type == DealType::Sol ||
+            forge_mint.key() == escrow.mint @ EscrowError::InvalidDealType
+    )]
+    pub forge_mint: InterfaceAccount<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+    #[account(address = escrow.token_program_id() @ EscrowError::InvalidProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct GetRemainingAmount<'info> {
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct GetRemainingNet<'info> {
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct CanClose<'info> {
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // FORGE deals only; leave out for SOL deals
+    #[account(
+        constraint = escrow_token_vault.owner == escrow.key()
+            && escrow_token_vault.mint == escrow.mint @ EscrowError::Unauthorized
+    )]
+    pub escrow_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEscrow<'info> {
+    #[account(mut, close = initiator, has_one = initiator @ EscrowError::Unauthorized)]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: The escrow's own SOL vault, emptied and closed along with the escrow
+    #[account(
+        mut,
+        seeds = [b"sol_vault", escrow.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow.sol_vault_bump
+    )]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    /// CHECK: Must be the configured treasury; receives any dust above the vault's rent
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    
+    // FORGE deals only; must be empty so no tokens are stranded when the escrow goes
+    #[account(
+        constraint = escrow_token_vault.owner == escrow.key()
+            && escrow_token_vault.mint == escrow.mint @ EscrowError::Unauthorized
+    )]
+    pub escrow_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    // Optional permanent record, paid for by the initiator out of the returned rent
+    #[account(
+        init,
+        payer = initiator,
+        space = 8 + EscrowTombstone::INIT_SPACE,
+        seeds = [b"tombstone", escrow.escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub tombstone: Option<Account<'info, EscrowTombstone>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSolVault<'info> {
+    #[account(has_one = initiator @ EscrowError::Unauthorized)]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    /// CHECK: The escrow's own SOL vault
+    #[account(
+        mut,
+        seeds = [b"sol_vault", escrow.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow.sol_vault_bump
+    )]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Must be the configured treasury; receives any dust above the vault's rent
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct SweepStrandedVault<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Read through its fixed-layout prefix only, since escrows from before later
+    /// fields were appended do not deserialize as the current struct
+    #[account(seeds = [b"escrow", escrow_id.to_le_bytes().as_ref()], bump)]
+    pub escrow: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives the vault rent; must be the escrow's initiator
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+    
+    /// CHECK: The escrow's own SOL vault; legacy escrows never stored its bump
+    #[account(mut, seeds = [b"sol_vault", escrow_id.to_le_bytes().as_ref()], bump)]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    /// CHECK: Must be the configured treasury; receives any dust above the vault's rent
+    #[account(mut, address = config.treasury)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetBumps<'info> {
+    // Mint the token vault holds
+    pub forge_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct GetConfig<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct GetDisplayInfo<'info> {
+    pub escrow: Account<'info, Escrow>,
+    
+    // Required for FORGE deals to read the token decimals
+    #[account(address = escrow.mint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEscrow<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Mutable so an arbiter can receive the gas rebate
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    /// CHECK: Must be the escrow's initiator
+    #[account(mut, address = escrow.initiator)]
+    pub initiator: AccountInfo<'info>,
+    
+    // FORGE deals only
+    #[account(mut)]
+    pub escrow_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    // FORGE deals only; recreated at the signer's expense if the initiator closed it, so
+    // refunds always land
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = forge_mint,
+        associated_token::authority = initiator,
+        associated_token::token_program = token_program
+    )]
+    pub initiator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    // FORGE deals only
+    #[account(address = escrow.mint @ EscrowError::InvalidDealType)]
+    pub forge_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    pub system_program: Program<'info, System>,
+    #[account(address = escrow.token_program_id() @ EscrowError::InvalidProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_escrow_id: u64)]
+pub struct CancelAndReinit<'info> {
+    #[account(mut, has_one = initiator @ EscrowError::Unauthorized)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Takes over the old escrow's funds under the new terms
+    #[account(
         init,
         payer = initiator,
         space = 8 + Escrow::INIT_SPACE,
-        seeds = [b"escrow", escrow_id.to_le_bytes().as_ref()],
+        seeds = [b"escrow", new_escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_escrow: Account<'info, Escrow>,
+    
+    // Pays for the new escrow's accounts
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // The initiator's stats; required while a deposit limit is configured
+    #[account(mut, seeds = [b"user_stats", initiator.key().as_ref()], bump = user_stats.bump)]
+    pub user_stats: Option<Account<'info, UserStats>>,
+    
+    // The initiator's escrow index; the new escrow is listed in it when passed
+    #[account(mut, seeds = [b"user_index", initiator.key().as_ref()], bump = user_index.bump)]
+    pub user_index: Option<Account<'info, UserIndex>>,
+    
+    // The initiator's recipient whitelist, checked against the new recipient
+    /// CHECK: May not exist; deserialized in RecipientWhitelist::check
+    #[account(seeds = [b"recipient_whitelist", initiator.key().as_ref()], bump)]
+    pub recipient_whitelist: UncheckedAccount<'info>,
+    
+    /// CHECK: The old escrow's SOL vault
+    #[account(
+        mut,
+        seeds = [b"sol_vault", escrow.escrow_id.to_le_bytes().as_ref()],
+        bump = escrow.sol_vault_bump
+    )]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    /// CHECK: This is safe because we're only using it as a vault
+    #[account(
+        init,
+        payer = initiator,
+        space = 0,
+        seeds = [b"sol_vault", new_escrow_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_escrow_sol_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub escrow_token_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = forge_mint,
+        associated_token::authority = new_escrow,
+        associated_token::token_program = token_program,
+        constraint = new_escrow_token_vault.amount == 0 @ EscrowError::VaultNotEmpty
+    )]
+    pub new_escrow_token_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        constraint = escrow.deal_type == DealType::Sol ||
+            forge_mint.key() == escrow.mint @ EscrowError::InvalidDealType
+    )]
+    pub forge_mint: InterfaceAccount<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+    #[account(address = escrow.token_program_id() @ EscrowError::InvalidProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+    
+    // Arbiter; mutable to receive the gas rebate
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Checked against the escrow's SOL vault PDA
+    #[account(mut)]
+    pub escrow_sol_vault: AccountInfo<'info>,
+    
+    /// CHECK: Must be the escrow's recipient
+    #[account(mut, address = escrow.recipient)]
+    pub recipient: AccountInfo<'info>,
+    
+    /// CHECK: Must be the escrow's initiator
+    #[account(mut, address = escrow.initiator)]
+    pub initiator: AccountInfo<'info>,
+    
+    /// CHECK: Must be the configured treasury
+    #[account(mut, address = config.treasury)]
+    pub fee_wallet: AccountInfo<'info>,
+
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub temp_fee_wallet: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub escrow_token_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut, token::authority = escrow.recipient)]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut, token::authority = escrow.initiator)]
+    pub initiator_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = forge_mint,
+        associated_token::authority = config.treasury,
+        associated_token::token_program = token_program
+    )]
+    pub fee_wallet_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = escrow.deal_type == DealType::Sol ||
+            burn_token_account.mint == escrow.mint @ EscrowError::InvalidBurnAddress,
+        constraint = burn_token_account.key() != fee_wallet_token_account.key() @ EscrowError::InvalidFeeWallet
+    )]
+    pub burn_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub forge_mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: Must be the escrow's referrer; required when it has one (SOL deals)
+    #[account(mut, constraint = escrow.referrer == Some(referrer.key()) @ EscrowError::InvalidReferrer)]
+    pub referrer: Option<UncheckedAccount<'info>>,
+    
+    // Required when the escrow has a referrer (FORGE deals)
+    #[account(
+        mut,
+        constraint = escrow.referrer == Some(referrer_token_account.owner) @ EscrowError::InvalidReferrer,
+        token::mint = forge_mint
+    )]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    // SOL deals: receives the insurance slice of the fee; required while one is configured
+    #[account(mut, seeds = [b"insurance_pool"], bump = insurance_pool.bump)]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    
+    pub system_program: Program<'info, System>,
+    #[account(address = escrow.token_program_id() @ EscrowError::InvalidProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct CreateEscrowPool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EscrowPool::INIT_SPACE,
+        seeds = [b"pool", pool_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, EscrowPool>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddPooledEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool", pool.pool_id.to_le_bytes().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, EscrowPool>,
+    
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    // The initiator's stats; required while a deposit limit is configured
+    #[account(mut, seeds = [b"user_stats", initiator.key().as_ref()], bump = user_stats.bump)]
+    pub user_stats: Option<Account<'info, UserStats>>,
+    
+    // The initiator's recipient whitelist, checked against the pooled recipient
+    /// CHECK: May not exist; deserialized in RecipientWhitelist::check
+    #[account(seeds = [b"recipient_whitelist", initiator.key().as_ref()], bump)]
+    pub recipient_whitelist: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleasePooledEscrow<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, EscrowPool>,
+    
+    pub signer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Checked against the pooled entry
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+    
+    /// CHECK: Must be the configured treasury
+    #[account(mut, address = config.treasury)]
+    pub fee_wallet: AccountInfo<'info>,
+
+    /// CHECK: Safe for SOL operations
+    #[account(mut)]
+    pub temp_fee_wallet: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPooledEscrow<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, EscrowPool>,
+    
+    pub signer: Signer<'info>,
+    
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Checked against the pooled entry
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserStats<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct CreateTemplate<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Template::INIT_SPACE,
+        seeds = [b"template", creator.key().as_ref(), template_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub escrow: Account<'info, Escrow>,
+    pub template: Account<'info, Template>,
     
     #[account(mut)]
-    pub initiator: Signer<'info>,
+    pub creator: Signer<'info>,
     
-    /// CHECK: This is safe because we're only using it as a vault
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserIndex<'info> {
     #[account(
         init,
-        payer = initiator,
-        space = 0,
-        seeds = [b"sol_vault", escrow_id.to_le_bytes().as_ref()],
+        payer = user,
+        space = 8 + UserIndex::INIT_SPACE,
+        seeds = [b"user_index", user.key().as_ref()],
         bump
     )]
-    pub escrow_sol_vault: AccountInfo<'info>,
+    pub user_index: Account<'info, UserIndex>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
     
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRecipientWhitelist<'info> {
     #[account(
         init_if_needed,
-        payer = initiator,
-        associated_token::mint = forge_mint,
-        associated_token::authority = escrow
+        payer = owner,
+        space = 8 + RecipientWhitelist::INIT_SPACE,
+        seeds = [b"recipient_whitelist", owner.key().as_ref()],
+        bump
     )]
-    pub escrow_token_vault: Account<'info, TokenAccount>,
+    pub recipient_whitelist: Account<'info, RecipientWhitelist>,
     
-    // Add initiator's token account for FORGE transfers
     #[account(mut)]
-    pub initiator_token_account: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
     
-    pub forge_mint: Account<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 #[derive(Accounts)]
-pub struct ReleaseFunds<'info> {
-    #[account(mut)]
-    pub escrow: Account<'info, Escrow>,
-    
-    pub signer: Signer<'info>,
-    
-    /// CHECK: Safe for SOL operations
-    #[account(mut)]
-    pub escrow_sol_vault: AccountInfo<'info>,
+#[instruction(code: String)]
+pub struct RegisterReferralCode<'info> {
+    #[account(
+        init,
+        payer = referrer,
+        space = 8 + ReferralCode::INIT_SPACE,
+        seeds = [b"referral_code", code.as_bytes()],
+        bump
+    )]
+    pub referral_code: Account<'info, ReferralCode>,
     
-    /// CHECK: Safe for SOL operations
     #[account(mut)]
-    pub recipient: AccountInfo<'info>,
+    pub referrer: Signer<'info>,
     
-    /// CHECK: Safe for SOL operations
-    #[account(mut)]
-    pub fee_wallet: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: Safe for SOL operations
-    #[account(mut)]
-    pub temp_fee_wallet: AccountInfo<'info>,
+#[derive(Accounts)]
+pub struct GetUserEscrows<'info> {
+    pub user_index: Account<'info, UserIndex>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePendingBurn<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PendingBurn::INIT_SPACE,
+        seeds = [b"pending_burn"],
+        bump
+    )]
+    pub pending_burn: Account<'info, PendingBurn>,
     
-    #[account(mut)]
-    pub escrow_token_vault: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
     
     #[account(mut)]
-    pub recipient_token_account: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
     
-    #[account(mut)]
-    pub fee_wallet_token_account: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
 
-    #[account(mut)]
-    pub burn_token_account: Account<'info, TokenAccount>,
+#[derive(Accounts)]
+pub struct InitializeInsurancePool<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + InsurancePool::INIT_SPACE,
+        seeds = [b"insurance_pool"],
+        bump
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+    
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
     
     #[account(mut)]
-    pub forge_mint: Account<'info, Mint>,
+    pub admin: Signer<'info>,
     
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct GetRemainingAmount<'info> {
-    pub escrow: Account<'info, Escrow>,
+pub struct ClaimInsurance<'info> {
+    #[account(mut, seeds = [b"insurance_pool"], bump = insurance_pool.bump)]
+    pub insurance_pool: Account<'info, InsurancePool>,
+    
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Receives the claimed lamports; must match the announced claim
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CancelEscrow<'info> {
-    #[account(mut)]
-    pub escrow: Account<'info, Escrow>,
+pub struct ProcessPendingBurn<'info> {
+    #[account(mut, seeds = [b"pending_burn"], bump = pending_burn.bump)]
+    pub pending_burn: Account<'info, PendingBurn>,
     
-    pub signer: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
     
-    /// CHECK: Safe for SOL operations
-    #[account(mut)]
-    pub escrow_sol_vault: AccountInfo<'info>,
+    pub admin: Signer<'info>,
     
     /// CHECK: Safe for SOL operations
     #[account(mut)]
-    pub initiator: AccountInfo<'info>,
+    pub temp_fee_wallet: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ EscrowError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
     
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeCap<'info> {
     #[account(mut)]
-    pub escrow_token_vault: Account<'info, TokenAccount>,
+    pub escrow: Account<'info, Escrow>,
     
-    #[account(mut)]
-    pub initiator_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ EscrowError::Unauthorized)]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
     
-    pub token_program: Program<'info, Token>,
+    pub new_admin: Signer<'info>,
 }
 
 // Data Structures
+pub const MAX_MILESTONES: usize = 10;
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+pub const MAX_POOL_ENTRIES: usize = 16;
+pub const MAX_ALLOWED_INITIATORS: usize = 32;
+pub const MAX_CONTRIBUTORS: usize = 8;
+pub const MAX_PRICE_AGE_SECS: u64 = 60;
+pub const SOL_DECIMALS: u8 = 9;
+pub const MAX_LABEL_LEN: usize = 64;
+pub const MAX_OVERRIDE_RECIPIENTS: usize = 16;
+pub const MAX_FEE_TIERS: usize = 4;
+pub const DEFAULT_FEE_BPS: u16 = 1_000;
+pub const MAX_ARBITER_GAS_REBATE: u64 = 100_000;
+pub const MAX_RECIPIENT_HOOKS: usize = 8;
+pub const MAX_STAKING_PROGRAMS: usize = 8;
+pub const MAX_ALLOWED_MINTS: usize = 8;
+pub const MAX_USER_ESCROWS: usize = 64;
+pub const MAX_USER_ESCROWS_PAGE: u8 = 32;
+// Keeps a batch of EscrowDetails (123 bytes each) within the 1024-byte return data limit
+pub const MAX_ESCROW_DETAILS_BATCH: usize = 8;
+pub const MAX_TOKEN_PROGRAMS: usize = 4;
+pub const MAX_METADATA_HISTORY: usize = 4;
+pub const MAX_REFERRAL_CODE_LEN: usize = 16;
+pub const MAX_WHITELISTED_RECIPIENTS: usize = 16;
+pub const INSURANCE_CLAIM_TIMELOCK_SECS: i64 = 2 * 24 * 60 * 60;
+
+// Compute unit limits clients should request (via a ComputeBudget instruction) for the
+// heavier instructions, with headroom over what they measure at their maximum sizes
+#[constant]
+pub const INITIALIZE_ESCROW_COMPUTE_UNITS: u32 = 120_000;
+#[constant]
+pub const RELEASE_SPLIT_COMPUTE_UNITS: u32 = 150_000;
+
+// Bumped whenever the rules new escrows are created under change
+#[constant]
+pub const ESCROW_SCHEMA_VERSION: u8 = 1;
+
+// Byte offset of `arbiter` in an Escrow account (discriminator + escrow_id + initiator +
+// recipient), for memcmp filters that fetch an arbiter's escrows
+#[constant]
+pub const ESCROW_ARBITER_OFFSET: usize = 80;
+
+// Byte offset of the one-byte `status` discriminant (arbiter + amount + released_amount +
+// deal_type), for memcmp filters on escrow status
+#[constant]
+pub const ESCROW_STATUS_OFFSET: usize = 129;
+
+// Carried first in every event; bumped whenever any event's fields change
+#[constant]
+pub const EVENT_SCHEMA_VERSION: u8 = 1;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Escrow {
+    // Fields up to `status` are fixed-size so `arbiter` and `status` stay at
+    // ESCROW_ARBITER_OFFSET and ESCROW_STATUS_OFFSET
     pub escrow_id: u64,
     pub initiator: Pubkey,      // Person paying (Alice)
     pub recipient: Pubkey,      // Person receiving payment (Bob)
-    pub arbiter: Pubkey,        // Third party who can resolve disputes
+    pub arbiter: Pubkey,        // Third party who can resolve disputes (default = none)
     pub amount: u64,            // Total amount to be paid
     pub released_amount: u64,   // Amount already released
     pub deal_type: DealType,    // SOL or FORGE tokens
     pub status: EscrowStatus,   // Current status
     pub bump: u8,               // PDA bump
+    #[max_len(MAX_MILESTONES)]
+    pub milestones: Vec<Milestone>, // Optional payment schedule, empty for plain escrows
+    pub last_release_nonce: u64, // Highest release nonce used so far (0 = none)
+    pub auto_stake_program: Option<Pubkey>, // Recipient opt-in: stake FORGE releases here
+    #[max_len(MAX_SPLIT_RECIPIENTS)]
+    pub split_recipients: Vec<SplitRecipient>, // Payees for release_split, empty if unused
+    pub accept_deadline: i64,   // Recipient must accept by this time (0 = no acceptance needed)
+    pub accepted: bool,         // Recipient has accepted the deal
+    pub sol_vault_bump: u8,     // Bump of the SOL vault PDA
+    pub fee_at_funding: bool,   // Fee was taken at funding; releases move principal only
+    #[max_len(MAX_CONTRIBUTORS)]
+    pub contributions: Vec<Contribution>, // Third-party top-ups, refunded proportionally on cancel
+    pub mint: Pubkey,           // Token mint for FORGE deals (default for SOL)
+    pub release_at: i64,        // Scheduled payout time (0 = not scheduled)
+    pub usd_amount: u64,        // Locked USD value, 6 decimals (0 = denominated in lamports)
+    pub usd_released_amount: u64, // USD value already paid out
+    pub expires_at: i64,        // Initiator may claim the remainder back after this (0 = never)
+    pub expiry_charges_fee: bool, // Expiry refunds pay the platform fee
+    #[max_len(0)]
+    pub label: String,          // Optional label; the account is grown to fit it by add_label
+    pub referrer: Option<Pubkey>, // Affiliate paid a cut of the platform fee
+    pub referrer_bps: u16,      // Referrer's share of the platform fee, in basis points
+    pub paused_by: Option<Pubkey>, // Party that paused releases and cancels, if any
+    pub schema_version: u8,     // Rules version at creation
+    pub finalized_at: i64,      // When the escrow was Released or Cancelled (0 = still open)
+    pub recipient_hook: Option<Pubkey>, // Program called by release_funds after paying out
+    pub auto_accept_at: i64,    // Counts as accepted from this time if still unaccepted (0 = never)
+    pub fees_paid: u64,         // Platform fees charged so far, including any referrer cut
+    pub fee_cap_absolute: u64,  // Most any single release pays in fees (0 = uncapped)
+    pub funded_at: i64,         // When the escrow was funded, for the cooling-off window
+    pub token_program: Pubkey,  // Token program its CPIs go through (default = SPL Token)
+    pub pending_release: u8,    // Percentage the recipient asked the arbiter to release (0 = none)
+    pub terms_uri_hash: [u8; 32], // Hash of the off-chain terms document (zero = none)
+    #[max_len(MAX_METADATA_HISTORY)]
+    pub metadata_history: Vec<[u8; 32]>, // Hashes of replaced label + terms, oldest first
+    pub release_condition: Option<ReleaseCondition>, // External state release_funds requires (None = unconditional)
+    pub require_both: bool,     // Every payout needs release_at to have passed and the arbiter's signature
+}
+
+// What is left of an escrow after close_escrow
+#[account]
+#[derive(InitSpace)]
+pub struct EscrowTombstone {
+    pub escrow_id: u64,
+    pub status: EscrowStatus,   // Final status (Released or Cancelled)
+    pub released_amount: u64,   // Total released over the escrow's life
+    pub fees_paid: u64,         // Total platform fees charged
+    pub closed_at: i64,         // When the escrow account was closed
+    pub bump: u8,               // PDA bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Contribution {
+    pub contributor: Pubkey,    // Wallet that topped up the escrow
+    pub amount: u64,            // Total contributed by this wallet
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct SplitRecipient {
+    pub wallet: Pubkey,  // Payee wallet (token account owner for FORGE)
+    pub bps: u16,        // Share of each split release in basis points
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct EscrowDetails {
+    pub escrow_id: u64,
+    pub initiator: Pubkey,
+    pub recipient: Pubkey,
+    pub arbiter: Pubkey,
+    pub amount: u64,
+    pub released_amount: u64,
+    pub deal_type: DealType,
+    pub status: EscrowStatus,
+    pub schema_version: u8,     // Rules version the escrow was created under
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct EscrowBumps {
+    pub escrow: u8,
+    pub sol_vault: u8,
+    pub token_vault: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ConfigDetails {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub permissioned: bool,
+    pub total_value_locked_sol: u64,
+    pub total_value_locked_token: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct DisplayInfo {
+    pub deal_type: DealType,    // SOL or FORGE
+    pub amount: u64,            // Raw amount in lamports or token base units
+    pub decimals: u8,           // Decimals to format `amount` with
+    pub label: String,          // Asset symbol, e.g. "SOL"
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ReleaseResult {
+    pub gross: u64,             // Taken out of the escrow, fee included
+    pub net: u64,               // Paid to the recipient
+    pub fee: u64,               // Sent to the fee wallet
+    pub burn: u64,              // Burned (FORGE) or set aside for buy-and-burn (SOL)
+    pub new_status: EscrowStatus,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,              // Portion of the escrow amount
+    pub status: MilestoneStatus,  // Current status
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MilestoneStatus {
+    Pending,    // Waiting for release or cancellation
+    Released,   // Paid out to recipient
+    Cancelled,  // Returned to initiator
+    Disputed,   // Frozen until the arbiter resolves it
+}
+
+// Program-wide settings, managed by the admin
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,          // Can update the config
+    pub treasury: Pubkey,       // Receives the treasury half of the platform fee
+    pub bump: u8,               // PDA bump
+    pub permissioned: bool,     // Only allowlisted initiators may create escrows
+    #[max_len(MAX_ALLOWED_INITIATORS)]
+    pub initiator_allowlist: Vec<Pubkey>,
+    pub price_feed: Pubkey,     // Pyth SOL/USD feed for USD-denominated escrows
+    pub pending_admin: Option<Pubkey>, // Proposed admin awaiting accept_admin
+    #[max_len(MAX_OVERRIDE_RECIPIENTS)]
+    pub override_allowlist: Vec<Pubkey>, // Wallets arbiters may redirect releases to (empty = any)
+    #[max_len(MAX_FEE_TIERS)]
+    pub fee_tiers: Vec<FeeTier>, // Volume-based fee discounts
+    pub arbiter_gas_rebate: u64, // Lamports paid to an arbiter who cancels or settles a SOL escrow
+    pub close_grace_period: i64, // Seconds a finished escrow stays open before close_escrow
+    #[max_len(MAX_RECIPIENT_HOOKS)]
+    pub hook_allowlist: Vec<Pubkey>, // Programs recipients may register as release hooks
+    pub total_value_locked_sol: u64,   // Lamports held across all escrows and pools
+    pub total_value_locked_token: u64, // FORGE base units held across all escrows
+    pub max_deadline_duration: i64, // Furthest an escrow's expiry may be from creation, in seconds (0 = no cap)
+    pub deposit_window: i64,    // Length of the per-initiator deposit window, in seconds (0 = no limit)
+    pub deposit_limit_per_window_sol: u64,   // Lamports one initiator may lock per window (0 = no limit)
+    pub deposit_limit_per_window_token: u64, // FORGE base units one initiator may lock per window (0 = no limit)
+    pub allow_self_arbiter: bool, // Initiators may be their own arbiter (test/dev deployments only)
+    pub token_price_feed: Pubkey, // Pyth FORGE/USD feed for USD-amount token releases
+    pub cooling_off_period: i64, // Seconds after funding the initiator may cancel despite a pause (0 = off)
+    #[max_len(MAX_TOKEN_PROGRAMS)]
+    pub token_program_allowlist: Vec<Pubkey>, // Alternate token programs escrows may use besides SPL Token
+    pub insurance_bps: u16,     // Share of SOL release fees sent to the insurance pool (0 = none)
+    pub dust_threshold_sol: u64, // Lamports below which release_funds sweeps the remainder (0 = off)
+    pub dust_threshold_token: u64, // Same for token escrows, in base units
+    #[max_len(MAX_ALLOWED_MINTS)]
+    pub mint_allowlist: Vec<Pubkey>, // Mints token escrows may use (empty = any)
+    pub fee_discount_window: i64, // Seconds after funding over which the fee discount grows (0 = off)
+    pub max_fee_discount_bps: u16, // Share of the fee waived once the window has passed
+    pub max_allowed_fee_bps: u16, // Ceiling on any fee rate charged; DEFAULT_FEE_BPS until lowered
+    #[max_len(MAX_STAKING_PROGRAMS)]
+    pub staking_allowlist: Vec<Pubkey>, // Programs recipients may auto-stake releases into
+}
+
+impl Config {
+    fn capped_fee_bps(&self, fee_bps: u16) -> u16 {
+        fee_bps.min(self.max_allowed_fee_bps)
+    }
+
+    // Fee after the discount earned by how long the escrow has been funded
+    fn time_discounted_fee_bps(&self, fee_bps: u16, funded_at: i64, now: i64) -> u16 {
+        if self.fee_discount_window == 0 {
+            return fee_bps;
+        }
+        let elapsed = (now - funded_at).clamp(0, self.fee_discount_window) as u128;
+        let discount_bps =
+            self.max_fee_discount_bps as u128 * elapsed / self.fee_discount_window as u128;
+        (fee_bps as u128 * (10_000 - discount_bps) / 10_000) as u16
+    }
+
+    fn is_mint_allowed(&self, mint: &Pubkey) -> bool {
+        self.mint_allowlist.is_empty() || self.mint_allowlist.contains(mint)
+    }
+
+    // Creation rules every new escrow is held to, whichever instruction creates it
+    fn validate_new_escrow(
+        &self,
+        initiator: &Pubkey,
+        arbiter: Option<Pubkey>,
+        expires_at: i64,
+        deal_type: &DealType,
+        mint: &Pubkey,
+        now: i64,
+    ) -> Result<()> {
+        require!(
+            !self.permissioned || self.initiator_allowlist.contains(initiator),
+            EscrowError::InitiatorNotAllowed
+        );
+        require!(
+            self.allow_self_arbiter || arbiter != Some(*initiator),
+            EscrowError::SelfArbiter
+        );
+        require!(expires_at == 0 || expires_at > now, EscrowError::InvalidDeadline);
+        require!(
+            expires_at == 0 ||
+            self.max_deadline_duration == 0 ||
+            expires_at <= now + self.max_deadline_duration,
+            EscrowError::DeadlineTooFar
+        );
+        require!(
+            *deal_type == DealType::Sol || self.is_mint_allowed(mint),
+            EscrowError::MintNotAllowed
+        );
+        Ok(())
+    }
+
+    // Returns (insurance fee, remaining half fee), taken off both halves like the referrer cut
+    fn insurance_fee_split(&self, half_fee: u64) -> (u64, u64) {
+        let fee = 2 * half_fee;
+        let insurance_fee = (fee as u128 * self.insurance_bps as u128 / 10_000) as u64;
+        let remaining_half = (fee - insurance_fee) / 2;
+        (fee - 2 * remaining_half, remaining_half)
+    }
+
+    fn dust_threshold(&self, deal_type: &DealType) -> u64 {
+        match deal_type {
+            DealType::Sol => self.dust_threshold_sol,
+            DealType::Forge => self.dust_threshold_token,
+        }
+    }
+
+    fn deposit_limit_per_window(&self, deal_type: &DealType) -> u64 {
+        match deal_type {
+            DealType::Sol => self.deposit_limit_per_window_sol,
+            DealType::Forge => self.deposit_limit_per_window_token,
+        }
+    }
+
+    fn lock_value(&mut self, deal_type: &DealType, amount: u64) {
+        match deal_type {
+            DealType::Sol => self.total_value_locked_sol += amount,
+            DealType::Forge => self.total_value_locked_token += amount,
+        }
+    }
+
+    fn unlock_value(&mut self, deal_type: &DealType, amount: u64) {
+        match deal_type {
+            DealType::Sol => self.total_value_locked_sol -= amount,
+            DealType::Forge => self.total_value_locked_token -= amount,
+        }
+    }
+}
+
+// Another program's account state a release waits for, e.g. a delivery tracker's flag
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ReleaseCondition {
+    pub account: Pubkey,        // Account to read
+    pub offset: u32,            // Byte offset into its data
+    pub expected: u8,           // Value that byte must hold
+}
+
+// Mint and extra accounts for a transfer_checked through a Token-2022 transfer hook
+struct TransferHook<'a, 'info> {
+    mint: AccountInfo<'info>,
+    decimals: u8,
+    extra_accounts: &'a [AccountInfo<'info>],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct FeeTier {
+    pub deal_type: DealType,    // Volume of this deal type counts toward the tier
+    pub min_volume: u64,        // Released volume needed to qualify, in base units
+    pub fee_bps: u16,           // Total platform fee for qualifying releases
+}
+
+// Per-initiator released volume, used for fee tiers
+#[account]
+#[derive(InitSpace)]
+pub struct UserStats {
+    pub user: Pubkey,           // Initiator whose escrows are counted
+    pub sol_volume: u64,        // Lamports released from their SOL escrows
+    pub forge_volume: u64,      // Tokens released from their FORGE escrows
+    pub bump: u8,               // PDA bump
+    pub deposit_window_start: i64,   // Start of the current deposit-limit window
+    pub window_deposited_sol: u64,   // Lamports locked in the current window
+    pub window_deposited_token: u64, // FORGE base units locked in the current window
+}
+
+// Reusable deal terms for initialize_from_template
+#[account]
+#[derive(InitSpace)]
+pub struct Template {
+    pub creator: Pubkey,
+    pub template_id: u64,
+    pub deal_type: DealType,
+    pub arbiter: Option<Pubkey>,  // None for two-party deals
+    pub recipient: Pubkey,
+    pub fee_at_funding: bool,
+    pub valid_until: i64,       // Unusable after this time (0 = never expires)
+    pub bump: u8,               // PDA bump
+}
+
+// IDs of the escrows a user has initiated, in creation order
+#[account]
+#[derive(InitSpace)]
+pub struct UserIndex {
+    pub user: Pubkey,           // Initiator whose escrows are listed
+    #[max_len(MAX_USER_ESCROWS)]
+    pub escrow_ids: Vec<u64>,
+    pub bump: u8,               // PDA bump
+    pub next_id: u64,           // Escrows created through the index; a per-user count, not an escrow ID
+}
+
+impl UserIndex {
+    fn register(&mut self, escrow_id: u64) -> Result<()> {
+        require!(self.escrow_ids.len() < MAX_USER_ESCROWS, EscrowError::UserIndexFull);
+        self.escrow_ids.push(escrow_id);
+        self.next_id += 1;
+        Ok(())
+    }
+}
+
+impl UserStats {
+    fn volume(&self, deal_type: &DealType) -> u64 {
+        match deal_type {
+            DealType::Sol => self.sol_volume,
+            DealType::Forge => self.forge_volume,
+        }
+    }
+
+    // While a deposit limit is configured the depositor's stats are required; a new window
+    // starts once the old one ends
+    fn record_deposit(
+        user_stats: Option<&mut UserStats>,
+        config: &Config,
+        deal_type: &DealType,
+        amount: u64,
+        now: i64,
+    ) -> Result<()> {
+        let limit = config.deposit_limit_per_window(deal_type);
+        if config.deposit_window == 0 || limit == 0 {
+            return Ok(());
+        }
+        let user_stats = user_stats.ok_or(EscrowError::MissingUserStats)?;
+
+        if now >= user_stats.deposit_window_start + config.deposit_window {
+            user_stats.deposit_window_start = now;
+            user_stats.window_deposited_sol = 0;
+            user_stats.window_deposited_token = 0;
+        }
+        let deposited = match deal_type {
+            DealType::Sol => &mut user_stats.window_deposited_sol,
+            DealType::Forge => &mut user_stats.window_deposited_token,
+        };
+        require!(*deposited + amount <= limit, EscrowError::DepositLimitExceeded);
+        *deposited += amount;
+        Ok(())
+    }
+}
+
+// Recipients an initiator's escrows may pay, when enabled
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientWhitelist {
+    pub owner: Pubkey,          // Initiator the whitelist applies to
+    pub enabled: bool,          // Enforced on new escrows and payout assignments while set
+    #[max_len(MAX_WHITELISTED_RECIPIENTS)]
+    pub recipients: Vec<Pubkey>,
+    pub bump: u8,               // PDA bump
+}
+
+impl RecipientWhitelist {
+    // An address with no account behind it means the initiator has no whitelist. Read from the
+    // raw data, with the owner and discriminator checks Account would make, so callers don't
+    // need to hold the account for 'info.
+    fn check(whitelist: &AccountInfo, recipient: &Pubkey) -> Result<()> {
+        if whitelist.data_is_empty() {
+            return Ok(());
+        }
+        require_keys_eq!(*whitelist.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+        let whitelist = RecipientWhitelist::try_deserialize(&mut &whitelist.try_borrow_data()?[..])?;
+        require!(
+            !whitelist.enabled || whitelist.recipients.contains(recipient),
+            EscrowError::RecipientNotWhitelisted
+        );
+        Ok(())
+    }
+}
+
+// Maps a referral code to the referrer it pays
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralCode {
+    pub referrer: Pubkey,       // Paid the referral cut on escrows created with the code
+    #[max_len(MAX_REFERRAL_CODE_LEN)]
+    pub code: String,
+    pub bump: u8,               // PDA bump
+}
+
+// Holds the buy-and-burn half of SOL release fees until a keeper processes it;
+// everything above the rent floor is pending
+#[account]
+#[derive(InitSpace)]
+pub struct PendingBurn {
+    pub total_processed: u64,   // Lamports processed for buy-and-burn so far
+    pub bump: u8,               // PDA bump
+}
+
+// Slice of SOL release fees set aside to cover losses from disputed or failed escrows;
+// everything above the rent floor is available to claim
+#[account]
+#[derive(InitSpace)]
+pub struct InsurancePool {
+    pub total_claimed: u64,     // Lamports paid out so far
+    pub claim_amount: u64,      // Announced payout (0 = none pending)
+    pub claim_destination: Pubkey, // Wallet the announced payout goes to
+    pub claim_unlocks_at: i64,  // When the announced payout may be claimed
+    pub bump: u8,               // PDA bump
+}
+
+// Many SOL micro-escrows sharing one account (and one rent deposit)
+#[account]
+#[derive(InitSpace)]
+pub struct EscrowPool {
+    pub pool_id: u64,
+    pub bump: u8,               // PDA bump
+    #[max_len(MAX_POOL_ENTRIES)]
+    pub entries: Vec<PooledEscrow>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct PooledEscrow {
+    pub initiator: Pubkey,      // Person paying
+    pub recipient: Pubkey,      // Person receiving payment
+    pub arbiter: Pubkey,        // Third party who can resolve disputes
+    pub amount: u64,            // Lamports held for this entry
+    pub status: EscrowStatus,   // Funded, Released or Cancelled
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
@@ -447,6 +5533,28 @@ pub enum EscrowStatus {
     Funded,       // Funds deposited, waiting for release
     Released,     // All funds released to recipient
     Cancelled,    // Escrow cancelled, funds returned to initiator
+    PartiallyReleased, // Some funds released, the remainder still held
+    Disputed,     // Frozen by a party until the arbiter settles
+}
+
+// Events
+#[event]
+pub struct StateSnapshot {
+    pub version: u8,            // EVENT_SCHEMA_VERSION at emit time
+    pub escrow: Pubkey,
+    pub escrow_id: u64,
+    pub state_hash: [u8; 32],   // SHA-256 of the serialized escrow state
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeCollected {
+    pub version: u8,            // EVENT_SCHEMA_VERSION at emit time
+    pub escrow_id: u64,
+    pub treasury_amount: u64,   // Sent to the fee wallet
+    pub burn_amount: u64,       // Burned (FORGE) or set aside for buy-and-burn (SOL)
+    pub mint: Option<Pubkey>,   // None for SOL
 }
 
 // Errors
@@ -467,5 +5575,159 @@ pub enum EscrowError {
     #[msg("No funds remaining to release")]
     NoFundsToRelease,
     #[msg("Invalid Burn Address")]
-    InvalidBurnAddress
+    InvalidBurnAddress,
+    #[msg("Invalid milestone configuration")]
+    InvalidMilestoneConfig,
+    #[msg("Milestone index out of range")]
+    InvalidMilestone,
+    #[msg("Milestone is not pending")]
+    InvalidMilestoneStatus,
+    #[msg("Milestone escrows must be released per milestone")]
+    MilestoneEscrow,
+    #[msg("Release nonce has already been used")]
+    DuplicateRelease,
+    #[msg("Staking program accounts do not match the recipient's opt-in")]
+    InvalidStakingProgram,
+    #[msg("Program account is not the canonical program")]
+    InvalidProgram,
+    #[msg("Invalid split recipient configuration")]
+    InvalidSplitConfig,
+    #[msg("Too many split recipients")]
+    TooManyRecipients,
+    #[msg("Deadline must be in the future")]
+    InvalidDeadline,
+    #[msg("Recipient has not accepted the escrow")]
+    NotAccepted,
+    #[msg("Acceptance window has closed")]
+    AcceptanceWindowClosed,
+    #[msg("Acceptance window is still open")]
+    AcceptanceWindowOpen,
+    #[msg("Escrow pool is full")]
+    PoolFull,
+    #[msg("Pooled escrow index out of range")]
+    InvalidPoolEntry,
+    #[msg("Initiator is not on the allowlist")]
+    InitiatorNotAllowed,
+    #[msg("Initiator allowlist is full")]
+    AllowlistFull,
+    #[msg("Fee accounts are required to charge the fee at funding")]
+    MissingFeeAccount,
+    #[msg("Too many contributors")]
+    TooManyContributors,
+    #[msg("Refund accounts do not match the escrow's contributors")]
+    InvalidContributor,
+    #[msg("No release has been scheduled")]
+    ReleaseNotScheduled,
+    #[msg("Scheduled release time has not been reached")]
+    ReleaseNotDue,
+    #[msg("Fee wallet must not be the burn account")]
+    InvalidFeeWallet,
+    #[msg("Escrow is denominated in USD")]
+    UsdEscrow,
+    #[msg("Escrow is not denominated in USD")]
+    NotUsdEscrow,
+    #[msg("USD amount must be greater than zero")]
+    InvalidUsdAmount,
+    #[msg("Invalid price feed")]
+    InvalidPriceFeed,
+    #[msg("Oracle price is stale")]
+    StalePrice,
+    #[msg("Escrow has no expiry")]
+    NoExpiry,
+    #[msg("Escrow has not expired yet")]
+    NotExpired,
+    #[msg("Label is too long")]
+    LabelTooLong,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Referrer account is required")]
+    MissingReferrerAccount,
+    #[msg("Escrow token vault already holds funds")]
+    VaultNotEmpty,
+    #[msg("Override recipient is not allowed")]
+    InvalidOverrideRecipient,
+    #[msg("Escrow is paused")]
+    EscrowPaused,
+    #[msg("Escrow is not paused")]
+    NotPaused,
+    #[msg("Percentage must be greater than zero")]
+    PercentageIsZero,
+    #[msg("Percentage must not exceed 100")]
+    PercentageTooHigh,
+    #[msg("Invalid fee tier configuration")]
+    InvalidFeeTiers,
+    #[msg("Arbiter gas rebate exceeds the maximum")]
+    GasRebateTooHigh,
+    #[msg("Escrow is still within its close grace period")]
+    GracePeriodActive,
+    #[msg("Recipient hook is not allowed")]
+    InvalidRecipientHook,
+    #[msg("Deadline is further out than the configured maximum")]
+    DeadlineTooFar,
+    #[msg("Nothing is pending for buy-and-burn")]
+    NothingToBurn,
+    #[msg("User stats account is required while a deposit limit is set")]
+    MissingUserStats,
+    #[msg("Deposit would exceed the initiator's limit for this window")]
+    DepositLimitExceeded,
+    #[msg("An account required for this deal type is missing")]
+    MissingDealAccount,
+    #[msg("The signer is not the approved delegate of the owner's token account")]
+    InvalidDelegate,
+    #[msg("The delegate is not approved for the full deposit")]
+    InsufficientDelegation,
+    #[msg("The user's escrow index is full")]
+    UserIndexFull,
+    #[msg("Too many entries requested in one page")]
+    PageTooLarge,
+    #[msg("A template account is required")]
+    MissingTemplate,
+    #[msg("The template is no longer valid")]
+    TemplateExpired,
+    #[msg("Withdrawal would leave less than the released or contributed amount")]
+    WithdrawExceedsOverfund,
+    #[msg("The initiator cannot be the escrow's arbiter")]
+    SelfArbiter,
+    #[msg("Only escrows with an arbiter can be disputed")]
+    NoArbiter,
+    #[msg("Token program is not allowlisted")]
+    TokenProgramNotAllowed,
+    #[msg("Too many token programs")]
+    TooManyTokenPrograms,
+    #[msg("No release request is pending")]
+    NoPendingRelease,
+    #[msg("Metadata can only change before any funds are released")]
+    MetadataLocked,
+    #[msg("Insurance share must be at most 10000 basis points")]
+    InvalidInsuranceBps,
+    #[msg("Insurance pool account is required")]
+    MissingInsurancePool,
+    #[msg("No insurance claim is pending")]
+    NoInsuranceClaim,
+    #[msg("Insurance claim is still timelocked")]
+    InsuranceClaimLocked,
+    #[msg("Condition account is required")]
+    MissingConditionAccount,
+    #[msg("Condition account does not match the escrow")]
+    InvalidConditionAccount,
+    #[msg("Release condition is not met")]
+    ReleaseConditionNotMet,
+    #[msg("Payout must be assigned to a different, non-default wallet")]
+    InvalidAssignee,
+    #[msg("Mint is not allowlisted")]
+    MintNotAllowed,
+    #[msg("Fee discount must be at most 10000 basis points")]
+    InvalidFeeDiscount,
+    #[msg("Escrow is disputed; only the arbiter's settlement can move its funds")]
+    EscrowDisputed,
+    #[msg("SOL vault is already closed")]
+    VaultAlreadyClosed,
+    #[msg("Referral code must be 1 to 16 bytes")]
+    InvalidReferralCode,
+    #[msg("Fee rate is above the governance cap")]
+    FeeTooHigh,
+    #[msg("Release requires the arbiter's approval")]
+    ArbiterApprovalRequired,
+    #[msg("Recipient is not on the initiator's whitelist")]
+    RecipientNotWhitelisted
 }
\ No newline at end of file
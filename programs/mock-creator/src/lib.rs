@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use esscrow::cpi::accounts::InitializeEscrow;
+use esscrow::program::Escrow as EscrowProgram;
+use esscrow::DealType;
+
+declare_id!("BWHoDtCvBB9e5vPJLPhceooEBvQrPADb8G986NgwJQ6p");
+
+// Minimal program that opens SOL escrows on users' behalf, funded from its own PDA
+#[program]
+pub mod mock_creator {
+    use super::*;
+
+    // Create and fund an escrow with the creator PDA as its initiator
+    pub fn create_escrow(
+        ctx: Context<CreateEscrow>,
+        escrow_id: u64,
+        amount: u64,
+        arbiter: Pubkey,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        let seeds = &[b"creator".as_ref(), &[ctx.bumps.creator]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            InitializeEscrow {
+                escrow: ctx.accounts.escrow.to_account_info(),
+                initiator: ctx.accounts.creator.to_account_info(),
+                token_owner: None,
+                config: ctx.accounts.config.to_account_info(),
+                user_stats: None,
+                template: None,
+                user_index: None,
+                referral_code: None,
+                recipient_whitelist: ctx.accounts.recipient_whitelist.to_account_info(),
+                escrow_sol_vault: ctx.accounts.escrow_sol_vault.to_account_info(),
+                escrow_token_vault: ctx.accounts.escrow_token_vault.to_account_info(),
+                initiator_token_account: ctx.accounts.initiator_token_account.to_account_info(),
+                forge_mint: ctx.accounts.forge_mint.to_account_info(),
+                fee_wallet: None,
+                temp_fee_wallet: None,
+                fee_wallet_token_account: None,
+                burn_token_account: None,
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            },
+            signer,
+        );
+        esscrow::cpi::initialize_escrow(
+            cpi_ctx,
+            escrow_id,
+            amount,
+            DealType::Sol,
+            Some(arbiter),
+            recipient,
+            false,
+            0,
+            false,
+            None,
+            0,
+            None,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateEscrow<'info> {
+    /// CHECK: System-owned PDA holding the lamports it deposits; signs as the initiator
+    #[account(mut, seeds = [b"creator"], bump)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow_sol_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow_token_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub initiator_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub forge_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub recipient_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub system_program: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub associated_token_program: UncheckedAccount<'info>,
+
+    pub escrow_program: Program<'info, EscrowProgram>,
+}
@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+use esscrow::cpi::accounts::ReleaseFunds;
+use esscrow::program::Escrow as EscrowProgram;
+
+declare_id!("A6gD6iZo7oGyQuGhvQdMnNs3yHfV9yUNR2KZbcSs9BTS");
+
+// Minimal governance program whose PDA acts as an escrow arbiter
+#[program]
+pub mod mock_governance {
+    use super::*;
+
+    // Execute an approved proposal by releasing escrowed SOL as the governance authority
+    pub fn execute_release(ctx: Context<ExecuteRelease>, percentage: u8) -> Result<()> {
+        let seeds = &[b"authority".as_ref(), &[ctx.bumps.authority]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            ReleaseFunds {
+                escrow: ctx.accounts.escrow.to_account_info(),
+                signer: ctx.accounts.authority.to_account_info(),
+                config: ctx.accounts.config.to_account_info(),
+                escrow_sol_vault: Some(ctx.accounts.escrow_sol_vault.to_account_info()),
+                recipient: Some(ctx.accounts.recipient.to_account_info()),
+                fee_wallet: Some(ctx.accounts.fee_wallet.to_account_info()),
+                temp_fee_wallet: Some(ctx.accounts.temp_fee_wallet.to_account_info()),
+                escrow_token_vault: None,
+                recipient_token_account: None,
+                fee_wallet_token_account: None,
+                burn_token_account: None,
+                referrer: None,
+                referrer_token_account: None,
+                user_stats: None,
+                pending_burn: None,
+                insurance_pool: None,
+                condition_account: None,
+                forge_mint: None,
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            signer,
+        );
+        esscrow::cpi::release_funds(cpi_ctx, percentage, None, None, None)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRelease<'info> {
+    /// CHECK: Governance PDA, only used as the arbiter signer
+    #[account(seeds = [b"authority"], bump)]
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow_sol_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub fee_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub temp_fee_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub system_program: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+
+    pub escrow_program: Program<'info, EscrowProgram>,
+}
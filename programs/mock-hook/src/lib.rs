@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+declare_id!("4G5VQ7w2uRMrYsQS1uMEpTeG8pPa9aJddVpMuhy635Ex");
+
+// Minimal recipient hook that records each release it is notified of
+#[program]
+pub mod mock_hook {
+    use super::*;
+
+    pub fn initialize_receipt(ctx: Context<InitializeReceipt>) -> Result<()> {
+        ctx.accounts.receipt.bump = ctx.bumps.receipt;
+        Ok(())
+    }
+
+    // Called by the escrow program after a release
+    pub fn on_escrow_release(ctx: Context<OnEscrowRelease>, escrow_id: u64, amount: u64) -> Result<()> {
+        let receipt = &mut ctx.accounts.receipt;
+
+        receipt.calls += 1;
+        receipt.escrow_id = escrow_id;
+        receipt.amount = amount;
+        receipt.recipient = ctx.accounts.recipient.key();
+
+        msg!("Hook received {} from escrow {}", amount, escrow_id);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeReceipt<'info> {
+    #[account(init, payer = payer, space = 8 + Receipt::INIT_SPACE, seeds = [b"receipt"], bump)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OnEscrowRelease<'info> {
+    /// CHECK: The escrow that paid out; passed for reference, never as a signer
+    pub escrow: UncheckedAccount<'info>,
+
+    /// CHECK: Paid by the escrow program
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"receipt"], bump = receipt.bump)]
+    pub receipt: Account<'info, Receipt>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Receipt {
+    pub calls: u64,
+    pub escrow_id: u64,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub bump: u8,
+}
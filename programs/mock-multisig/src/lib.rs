@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+use esscrow::cpi::accounts::AcceptEscrow;
+use esscrow::program::Escrow as EscrowProgram;
+
+declare_id!("BUSLBnR9QaDxjJ5rngpCHySMT3iGahhVzsBZZF11i5tu");
+
+// Minimal two-of-two multisig whose vault PDA can be an escrow recipient
+#[program]
+pub mod mock_multisig {
+    use super::*;
+
+    // Both members co-sign; the vault then accepts the escrow on their behalf
+    pub fn execute_accept(ctx: Context<ExecuteAccept>) -> Result<()> {
+        let member_a = ctx.accounts.member_a.key();
+        let member_b = ctx.accounts.member_b.key();
+        let seeds = &[
+            b"vault".as_ref(),
+            member_a.as_ref(),
+            member_b.as_ref(),
+            &[ctx.bumps.vault],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            AcceptEscrow {
+                escrow: ctx.accounts.escrow.to_account_info(),
+                recipient: ctx.accounts.vault.to_account_info(),
+            },
+            signer,
+        );
+        esscrow::cpi::accept_escrow(cpi_ctx)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAccept<'info> {
+    pub member_a: Signer<'info>,
+
+    pub member_b: Signer<'info>,
+
+    /// CHECK: Multisig vault PDA, only used as the recipient signer
+    #[account(seeds = [b"vault", member_a.key().as_ref(), member_b.key().as_ref()], bump)]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+
+    pub escrow_program: Program<'info, EscrowProgram>,
+}
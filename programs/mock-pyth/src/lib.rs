@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use pyth_sdk_solana::state::{AccountType, PriceStatus, SolanaPriceAccount, MAGIC, VERSION_2};
+
+declare_id!("HzWQv1t5NzMz9TaM7JX9Xa9XSYkT2RdKBdSGuD9u5uBh");
+
+// Minimal oracle for exercising the escrow USD settlement against a Pyth-format feed
+#[program]
+pub mod mock_pyth {
+    use super::*;
+
+    // Overwrite the feed with a trading aggregate price of `price * 10^expo`
+    pub fn set_price(ctx: Context<SetPrice>, price: i64, expo: i32, publish_time: i64) -> Result<()> {
+        let mut data = ctx.accounts.price_feed.try_borrow_mut_data()?;
+        let size = std::mem::size_of::<SolanaPriceAccount>();
+        require!(data.len() >= size, ErrorCode::AccountDidNotDeserialize);
+
+        let feed: &mut SolanaPriceAccount = bytemuck::from_bytes_mut(&mut data[..size]);
+        feed.magic = MAGIC;
+        feed.ver = VERSION_2;
+        feed.atype = AccountType::Price as u32;
+        feed.expo = expo;
+        feed.timestamp = publish_time;
+        feed.agg.price = price;
+        feed.agg.conf = 0;
+        feed.agg.status = PriceStatus::Trading;
+        feed.prev_price = price;
+        feed.prev_timestamp = publish_time;
+
+        msg!("Price set to {}e{}", price, expo);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    /// CHECK: Raw Pyth price account owned by this program
+    #[account(mut, owner = crate::ID)]
+    pub price_feed: UncheckedAccount<'info>,
+}
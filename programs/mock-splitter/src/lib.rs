@@ -0,0 +1,142 @@
+use anchor_lang::prelude::*;
+use esscrow::cpi::accounts::SetAutoStake;
+use esscrow::program::Escrow as EscrowProgram;
+
+declare_id!("B75fChqPqFcq2L5pNJP7uT41maqt8ocCmbvPxDs5hJWx");
+
+pub const MAX_PAYEES: usize = 4;
+
+// Minimal payment splitter whose PDA receives escrow releases and passes them on to its
+// payees by share
+#[program]
+pub mod mock_splitter {
+    use super::*;
+
+    pub fn create_splitter(
+        ctx: Context<CreateSplitter>,
+        payees: Vec<Pubkey>,
+        shares_bps: Vec<u16>,
+    ) -> Result<()> {
+        require!(
+            !payees.is_empty() && payees.len() <= MAX_PAYEES && payees.len() == shares_bps.len(),
+            SplitterError::InvalidShares
+        );
+        require!(
+            shares_bps.iter().map(|bps| *bps as u32).sum::<u32>() == 10_000,
+            SplitterError::InvalidShares
+        );
+
+        let splitter = &mut ctx.accounts.splitter;
+        splitter.payees = payees;
+        splitter.shares_bps = shares_bps;
+        splitter.bump = ctx.bumps.splitter;
+        Ok(())
+    }
+
+    // Sign as the splitter PDA to have the escrow call distribute-on-release
+    pub fn register_escrow_hook(ctx: Context<RegisterEscrowHook>) -> Result<()> {
+        let seeds = &[b"splitter".as_ref(), &[ctx.accounts.splitter.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            SetAutoStake {
+                escrow: ctx.accounts.escrow.to_account_info(),
+                recipient: ctx.accounts.splitter.to_account_info(),
+                config: ctx.accounts.config.to_account_info(),
+            },
+            signer,
+        );
+        esscrow::cpi::set_recipient_hook(cpi_ctx, Some(crate::ID))
+    }
+
+    // Called by the escrow program after paying the splitter.
+    // remaining_accounts: the payees, in order
+    pub fn on_escrow_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, OnEscrowRelease<'info>>,
+        escrow_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let splitter = &ctx.accounts.recipient;
+        require!(
+            ctx.remaining_accounts.len() == splitter.payees.len(),
+            SplitterError::InvalidPayee
+        );
+
+        // The last payee takes the rounding remainder
+        let mut distributed = 0u64;
+        for (i, payee) in ctx.remaining_accounts.iter().enumerate() {
+            require_keys_eq!(payee.key(), splitter.payees[i], SplitterError::InvalidPayee);
+            let share = if i + 1 == splitter.payees.len() {
+                amount - distributed
+            } else {
+                (amount as u128 * splitter.shares_bps[i] as u128 / 10_000) as u64
+            };
+            **splitter.to_account_info().try_borrow_mut_lamports()? -= share;
+            **payee.try_borrow_mut_lamports()? += share;
+            distributed += share;
+        }
+
+        msg!("Splitter distributed {} from escrow {}", amount, escrow_id);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateSplitter<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Splitter::INIT_SPACE,
+        seeds = [b"splitter"],
+        bump
+    )]
+    pub splitter: Account<'info, Splitter>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterEscrowHook<'info> {
+    #[account(seeds = [b"splitter"], bump = splitter.bump)]
+    pub splitter: Account<'info, Splitter>,
+
+    /// CHECK: Validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+
+    /// CHECK: Validated by the escrow program
+    pub config: UncheckedAccount<'info>,
+
+    pub escrow_program: Program<'info, EscrowProgram>,
+}
+
+#[derive(Accounts)]
+pub struct OnEscrowRelease<'info> {
+    /// CHECK: The escrow that paid out; passed for reference, never as a signer
+    pub escrow: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"splitter"], bump = recipient.bump)]
+    pub recipient: Account<'info, Splitter>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Splitter {
+    #[max_len(MAX_PAYEES)]
+    pub payees: Vec<Pubkey>,
+    #[max_len(MAX_PAYEES)]
+    pub shares_bps: Vec<u16>,
+    pub bump: u8,
+}
+
+#[error_code]
+pub enum SplitterError {
+    #[msg("Payees and shares must match and add up to 100%")]
+    InvalidShares,
+    #[msg("Payee accounts do not match the splitter")]
+    InvalidPayee
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+declare_id!("3Yehr8VkYkEemXVHd1HsRmNjpmMwy1ppkGLKkp2nPHnK");
+
+// Minimal staking program for exercising the escrow auto-stake CPI
+#[program]
+pub mod mock_staking {
+    use super::*;
+
+    // Pull tokens from the depositor into the stake vault on behalf of the beneficiary
+    pub fn deposit_for(ctx: Context<DepositFor>, amount: u64, beneficiary: Pubkey) -> Result<()> {
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.source.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        msg!("Staked {} for {}", amount, beneficiary);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct DepositFor<'info> {
+    pub depositor: Signer<'info>,
+
+    #[account(mut)]
+    pub source: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+declare_id!("A4ufbfopgkL41oPGXtERgQLMGPe4ffd8TYZh9jcbKbYi");
+
+// Minimal delivery tracker whose shipment flag escrows can be conditioned on
+#[program]
+pub mod mock_tracker {
+    use super::*;
+
+    pub fn create_shipment(ctx: Context<CreateShipment>) -> Result<()> {
+        let shipment = &mut ctx.accounts.shipment;
+
+        shipment.delivered = false;
+        shipment.carrier = ctx.accounts.carrier.key();
+        Ok(())
+    }
+
+    // Carrier flips the delivered flag, which sits right after the discriminator (offset 8)
+    pub fn set_delivered(ctx: Context<SetDelivered>, delivered: bool) -> Result<()> {
+        ctx.accounts.shipment.delivered = delivered;
+
+        msg!("Shipment {} delivered: {}", ctx.accounts.shipment.key(), delivered);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateShipment<'info> {
+    #[account(init, payer = carrier, space = 8 + Shipment::INIT_SPACE)]
+    pub shipment: Account<'info, Shipment>,
+
+    #[account(mut)]
+    pub carrier: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDelivered<'info> {
+    #[account(mut, has_one = carrier)]
+    pub shipment: Account<'info, Shipment>,
+
+    pub carrier: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Shipment {
+    pub delivered: bool,
+    pub carrier: Pubkey,
+}
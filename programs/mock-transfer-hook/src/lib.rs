@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{create_account, CreateAccount};
+
+declare_id!("9YQQscRABnEqbGEEnd1UttHFDtQxxAyvJsBQpUN3tLmS");
+
+// sha256("spl-transfer-hook-interface:execute")[..8], the discriminator Token-2022 calls the
+// hook with and the TLV type of the extra-account-metas list
+const EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+
+// TLV type + length, slice count, then one 35-byte ExtraAccountMeta
+const EXTRA_ACCOUNT_METAS_LEN: usize = 8 + 4 + 4 + 35;
+
+// Minimal Token-2022 transfer hook that counts transfers in a PDA, so escrow tests can check
+// the hook ran and that its extra account was resolved
+#[program]
+pub mod mock_transfer_hook {
+    use super::*;
+
+    // Writes the extra-account-metas list for `mint` by hand: the counter PDA, writable
+    pub fn initialize_extra_account_metas(ctx: Context<InitializeExtraAccountMetas>) -> Result<()> {
+        let mint = ctx.accounts.mint.key();
+        let seeds: &[&[u8]] = &[b"extra-account-metas", mint.as_ref(), &[ctx.bumps.extra_account_metas]];
+        create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                CreateAccount {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.extra_account_metas.to_account_info(),
+                },
+                &[seeds],
+            ),
+            Rent::get()?.minimum_balance(EXTRA_ACCOUNT_METAS_LEN),
+            EXTRA_ACCOUNT_METAS_LEN as u64,
+            &crate::ID,
+        )?;
+
+        let mut data = ctx.accounts.extra_account_metas.try_borrow_mut_data()?;
+        data[..8].copy_from_slice(&EXECUTE_DISCRIMINATOR);
+        data[8..12].copy_from_slice(&(4u32 + 35).to_le_bytes());
+        data[12..16].copy_from_slice(&1u32.to_le_bytes());
+        data[16] = 0; // Literal address
+        data[17..49].copy_from_slice(ctx.accounts.counter.key().as_ref());
+        data[49] = 0; // Not a signer
+        data[50] = 1; // Writable
+        Ok(())
+    }
+
+    pub fn initialize_counter(ctx: Context<InitializeCounter>) -> Result<()> {
+        ctx.accounts.counter.transfers = 0;
+        Ok(())
+    }
+
+    // Token-2022 calls Execute with [source, mint, destination, authority, extra-account-metas,
+    // ...extra accounts], which is not an Anchor instruction
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        require!(
+            data.get(..8) == Some(&EXECUTE_DISCRIMINATOR[..]),
+            ErrorCode::InstructionFallbackNotFound
+        );
+
+        let counter_info = accounts.get(5).ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        let mut counter = Account::<Counter>::try_from(counter_info)?;
+        counter.transfers += 1;
+        counter.exit(program_id)?;
+
+        msg!("Transfer hook executed ({} transfers)", counter.transfers);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetas<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Created and written by hand; Token-2022 reads it as a TLV list
+    #[account(mut, seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    pub extra_account_metas: UncheckedAccount<'info>,
+
+    /// CHECK: Only its address seeds the list
+    pub mint: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"counter"], bump)]
+    pub counter: Account<'info, Counter>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCounter<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(init, payer = payer, space = 8 + Counter::INIT_SPACE, seeds = [b"counter"], bump)]
+    pub counter: Account<'info, Counter>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Counter {
+    pub transfers: u64,
+}
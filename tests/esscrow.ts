@@ -1,16 +1,4668 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
-import { Esscrow } from "../target/types/esscrow";
+import { Program, BN } from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  approve,
+  closeAccount,
+  createAccount,
+  createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferHookInstruction,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getMintLen,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import { createHash } from "crypto";
+import {
+  ESCROW_ARBITER_OFFSET,
+  ESCROW_STATUS_OFFSET,
+  fetchDisputedEscrowsByArbiter,
+  fetchEscrowsByArbiter,
+} from "../app/escrows";
+import { Escrow } from "../target/types/escrow";
+import { MockCreator } from "../target/types/mock_creator";
+import { MockGovernance } from "../target/types/mock_governance";
+import { MockHook } from "../target/types/mock_hook";
+import { MockMultisig } from "../target/types/mock_multisig";
+import { MockPyth } from "../target/types/mock_pyth";
+import { MockSplitter } from "../target/types/mock_splitter";
+import { MockStaking } from "../target/types/mock_staking";
+import { MockTracker } from "../target/types/mock_tracker";
+import { MockTransferHook } from "../target/types/mock_transfer_hook";
 
-describe("esscrow", () => {
+const { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } = anchor.web3;
+
+describe("escrow", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+  const connection = provider.connection;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const initiator = Keypair.generate();
+  const recipient = Keypair.generate();
+  const arbiter = Keypair.generate();
+  const feeWallet = Keypair.generate();
+  const tempFeeWallet = Keypair.generate();
+  const burnWallet = Keypair.generate();
+
+  let forgeMint: anchor.web3.PublicKey;
+  let initiatorTokenAccount: anchor.web3.PublicKey;
+  let recipientTokenAccount: anchor.web3.PublicKey;
+  let feeWalletTokenAccount: anchor.web3.PublicKey;
+  let burnTokenAccount: anchor.web3.PublicKey;
+
+  let nextEscrowId = 1;
+  const dealTypes = new Map<number, "sol" | "forge">();
+
+  const escrowPda = (escrowId: BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), escrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const solVaultPda = (escrowId: BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), escrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const configPda = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  )[0];
+
+  const recipientWhitelistPda = (owner: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("recipient_whitelist"), owner.toBuffer()],
+      program.programId
+    )[0];
+
+  const tokenVault = (escrowId: BN) =>
+    getAssociatedTokenAddressSync(forgeMint, escrowPda(escrowId), true);
+
+  const airdrop = async (pubkey: anchor.web3.PublicKey, sol = 10) => {
+    const sig = await connection.requestAirdrop(pubkey, sol * LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(sig, "confirmed");
+  };
+
+  const balance = (pubkey: anchor.web3.PublicKey) =>
+    connection.getBalance(pubkey, "confirmed");
+
+  const tokenBalance = async (pubkey: anchor.web3.PublicKey) =>
+    Number((await getAccount(connection, pubkey, "confirmed")).amount);
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const chainTime = async () => {
+    const slot = await connection.getSlot("confirmed");
+    return (await connection.getBlockTime(slot)) ?? Math.floor(Date.now() / 1000);
+  };
+
+  const eventsOf = async (signature: string) => {
+    await connection.confirmTransaction(signature, "confirmed");
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return Array.from(parser.parseLogs(tx?.meta?.logMessages ?? []));
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+    } catch (err) {
+      assert.instanceOf(err, anchor.AnchorError);
+      assert.equal((err as anchor.AnchorError).error.errorCode.code, code);
+      return;
+    }
+    assert.fail(`Expected ${code}`);
+  };
+
+  type EscrowOptions = {
+    arbiter?: anchor.web3.PublicKey | null; // null for a two-party escrow
+    initiator?: anchor.web3.Keypair; // Signs and pays; a relayer when tokenOwner is set
+    tokenOwner?: anchor.web3.PublicKey; // Real owner of initiatorTokenAccount for delegated deposits
+    initiatorTokenAccount?: anchor.web3.PublicKey; // Required for FORGE deals with a custom initiator
+    recipient?: anchor.web3.PublicKey;
+    feeAtFunding?: boolean;
+    expiresAt?: number;
+    expiryChargesFee?: boolean;
+    referrer?: anchor.web3.PublicKey;
+    referrerBps?: number;
+    userStats?: anchor.web3.PublicKey;
+    userIndex?: anchor.web3.PublicKey;
+    template?: anchor.web3.PublicKey; // Create from these terms, ignoring the ones above
+    escrowId?: BN; // Reuse a specific ID instead of the next fresh one
+    mint?: anchor.web3.PublicKey; // FORGE deals in a mint other than forgeMint
+    tokenProgram?: anchor.web3.PublicKey; // Token program owning that mint
+    releaseCondition?: { account: anchor.web3.PublicKey; offset: number; expected: number };
+    remainingAccounts?: anchor.web3.AccountMeta[]; // e.g. the mint's transfer-hook accounts
+    referralCode?: anchor.web3.PublicKey; // Resolves the referrer on-chain
+  };
+
+  const createEscrow = async (
+    amount: number,
+    dealType: "sol" | "forge" = "sol",
+    options: EscrowOptions = {}
+  ) => {
+    const escrowId = options.escrowId ?? new BN(nextEscrowId++);
+    const escrow = escrowPda(escrowId);
+    const escrowInitiator = options.initiator ?? initiator;
+    const tokenProgram = options.tokenProgram ?? TOKEN_PROGRAM_ID;
+    const mint = options.mint ?? forgeMint;
+    dealTypes.set(escrowId.toNumber(), dealType);
+
+    const method = options.template
+      ? program.methods.initializeFromTemplate(escrowId, new BN(amount))
+      : program.methods.initializeEscrow(
+          escrowId,
+          new BN(amount),
+          dealType === "sol" ? { sol: {} } : { forge: {} },
+          options.arbiter === undefined ? arbiter.publicKey : options.arbiter,
+          options.recipient ?? recipient.publicKey,
+          !!options.feeAtFunding,
+          new BN(options.expiresAt ?? 0),
+          !!options.expiryChargesFee,
+          options.referrer ?? null,
+          options.referrerBps ?? 0,
+          options.releaseCondition ?? null
+        );
+    await method
+      .accounts({
+        escrow,
+        initiator: escrowInitiator.publicKey,
+        tokenOwner: options.tokenOwner ?? null,
+        config: configPda,
+        userStats: options.userStats ?? null,
+        template: options.template ?? null,
+        userIndex: options.userIndex ?? null,
+        referralCode: options.referralCode ?? null,
+        recipientWhitelist: recipientWhitelistPda(options.tokenOwner ?? escrowInitiator.publicKey),
+        escrowSolVault: solVaultPda(escrowId),
+        escrowTokenVault: getAssociatedTokenAddressSync(mint, escrow, true, tokenProgram),
+        initiatorTokenAccount: options.initiatorTokenAccount ?? initiatorTokenAccount,
+        forgeMint: mint,
+        feeWallet: options.feeAtFunding ? feeWallet.publicKey : null,
+        tempFeeWallet: options.feeAtFunding ? tempFeeWallet.publicKey : null,
+        feeWalletTokenAccount: options.feeAtFunding ? feeWalletTokenAccount : null,
+        burnTokenAccount: options.feeAtFunding ? burnTokenAccount : null,
+        systemProgram: SystemProgram.programId,
+        tokenProgram,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(options.remainingAccounts ?? [])
+      .signers([escrowInitiator])
+      .rpc();
+
+    return { escrowId, escrow };
+  };
+
+  const solReleaseAccounts = (escrowId: BN) => ({
+    escrowSolVault: solVaultPda(escrowId),
+    recipient: recipient.publicKey,
+    feeWallet: feeWallet.publicKey,
+    tempFeeWallet: tempFeeWallet.publicKey,
+  });
+
+  const forgeReleaseAccounts = (escrowId: BN) => ({
+    escrowTokenVault: tokenVault(escrowId),
+    recipientTokenAccount,
+    feeWalletTokenAccount,
+    burnTokenAccount,
+    forgeMint,
+  });
+
+  // Releases only take the accounts of the escrow's own deal type
+  const releaseAccounts = (escrowId: BN, signer = arbiter.publicKey) => ({
+    escrow: escrowPda(escrowId),
+    signer,
+    config: configPda,
+    escrowSolVault: null,
+    recipient: null,
+    feeWallet: null,
+    tempFeeWallet: null,
+    escrowTokenVault: null,
+    recipientTokenAccount: null,
+    feeWalletTokenAccount: null,
+    burnTokenAccount: null,
+    forgeMint: null,
+    ...(dealTypes.get(escrowId.toNumber()) === "forge"
+      ? forgeReleaseAccounts(escrowId)
+      : solReleaseAccounts(escrowId)),
+    referrer: null,
+    referrerTokenAccount: null,
+    userStats: null,
+    pendingBurn: null,
+    insurancePool: null,
+    conditionAccount: null,
+    systemProgram: SystemProgram.programId,
+    tokenProgram: TOKEN_PROGRAM_ID,
+  });
+
+  // Like releases, cancels only take token accounts for FORGE escrows
+  const cancelAccounts = (escrowId: BN, signer = arbiter.publicKey) => ({
+    escrow: escrowPda(escrowId),
+    signer,
+    config: configPda,
+    escrowSolVault: solVaultPda(escrowId),
+    initiator: initiator.publicKey,
+    ...(dealTypes.get(escrowId.toNumber()) === "forge"
+      ? { escrowTokenVault: tokenVault(escrowId), initiatorTokenAccount, forgeMint }
+      : { escrowTokenVault: null, initiatorTokenAccount: null, forgeMint: null }),
+    systemProgram: SystemProgram.programId,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+  });
+
+  const settleAccounts = (escrowId: BN, signer = arbiter.publicKey) => ({
+    ...releaseAccounts(escrowId, signer),
+    ...solReleaseAccounts(escrowId),
+    ...forgeReleaseAccounts(escrowId),
+    initiator: initiator.publicKey,
+    initiatorTokenAccount,
+  });
+
+  before(async () => {
+    for (const wallet of [initiator, recipient, arbiter, feeWallet, tempFeeWallet]) {
+      await airdrop(wallet.publicKey);
+    }
+
+    forgeMint = await createMint(connection, payer, payer.publicKey, null, 6);
+    initiatorTokenAccount = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      forgeMint,
+      initiator.publicKey
+    );
+    recipientTokenAccount = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      forgeMint,
+      recipient.publicKey
+    );
+    feeWalletTokenAccount = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      forgeMint,
+      feeWallet.publicKey
+    );
+    burnTokenAccount = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      forgeMint,
+      burnWallet.publicKey
+    );
+    await mintTo(connection, payer, forgeMint, initiatorTokenAccount, payer, 1_000_000_000_000);
+
+    await program.methods
+      .initializeConfig(feeWallet.publicKey)
+      .accounts({
+        config: configPda,
+        admin: payer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  describe("milestones", () => {
+    it("cancels a single milestone while the others remain releasable", async () => {
+      const { escrowId, escrow } = await createEscrow(3_000_000, "forge");
+
+      await program.methods
+        .setMilestones([new BN(1_000_000), new BN(1_000_000), new BN(1_000_000)])
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      const initiatorBefore = await tokenBalance(initiatorTokenAccount);
+      await program.methods
+        .cancelMilestone(1)
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.equal(await tokenBalance(initiatorTokenAccount), initiatorBefore + 1_000_000);
+
+      let state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 2_000_000);
+      assert.deepEqual(state.milestones[1].status, { cancelled: {} });
+      assert.deepEqual(state.status, { funded: {} });
+
+      const recipientBefore = await tokenBalance(recipientTokenAccount);
+      for (const index of [0, 2]) {
+        await program.methods
+          .releaseMilestone(index)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+      }
+      assert.equal(await tokenBalance(recipientTokenAccount), recipientBefore + 1_800_000);
+
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.milestones[0].status, { released: {} });
+      assert.deepEqual(state.milestones[2].status, { released: {} });
+      assert.equal(state.releasedAmount.toNumber(), 2_000_000);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("rejects cancelling a milestone that was already released", async () => {
+      const { escrowId, escrow } = await createEscrow(2_000_000);
+
+      await program.methods
+        .setMilestones([new BN(1_000_000), new BN(1_000_000)])
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await program.methods
+        .releaseMilestone(0)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .cancelMilestone(0)
+          .accounts(cancelAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "InvalidMilestoneStatus"
+      );
+    });
+  });
+
+  describe("release nonces", () => {
+    it("rejects a release that replays a used nonce", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .releaseFunds(10, new BN(7), null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .releaseFunds(20, new BN(7), null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "DuplicateRelease"
+      );
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 100_000);
+      assert.equal(state.lastReleaseNonce.toNumber(), 7);
+    });
+
+    it("allows releases without a nonce", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .releaseFunds(50, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 500_000);
+    });
+  });
+
+  describe("auto-stake", () => {
+    const mockStaking = anchor.workspace.MockStaking as Program<MockStaking>;
+    const stakePool = Keypair.generate();
+    let stakeVault: anchor.web3.PublicKey;
+
+    before(async () => {
+      stakeVault = await createAssociatedTokenAccount(
+        connection,
+        payer,
+        forgeMint,
+        stakePool.publicKey
+      );
+      await program.methods
+        .setStakingAllowlist([mockStaking.programId])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods
+        .setStakingAllowlist([])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    it("deposits the recipient's portion into the opted-in staking program", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+
+      await program.methods
+        .setAutoStake(mockStaking.programId)
+        .accounts({ escrow, recipient: recipient.publicKey, config: configPda })
+        .signers([recipient])
+        .rpc();
+
+      const recipientBefore = await tokenBalance(recipientTokenAccount);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .remainingAccounts([
+          { pubkey: mockStaking.programId, isWritable: false, isSigner: false },
+          { pubkey: stakeVault, isWritable: true, isSigner: false },
+        ])
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await tokenBalance(stakeVault), 900_000);
+      assert.equal(await tokenBalance(recipientTokenAccount), recipientBefore);
+    });
+
+    it("only lets the recipient opt in", async () => {
+      const { escrow } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .setAutoStake(mockStaking.programId)
+          .accounts({ escrow, recipient: arbiter.publicKey, config: configPda })
+          .signers([arbiter])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("rejects a staking program the admin has not allowlisted", async () => {
+      const { escrow } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .setAutoStake(Keypair.generate().publicKey)
+          .accounts({ escrow, recipient: recipient.publicKey, config: configPda })
+          .signers([recipient])
+          .rpc(),
+        "InvalidStakingProgram"
+      );
+    });
+  });
+
+  describe("program validation", () => {
+    it("rejects a substituted token program", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), tokenProgram: SystemProgram.programId })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidProgramId"
+      );
+    });
+
+    it("rejects a substituted system program on initialization", async () => {
+      const escrowId = new BN(nextEscrowId++);
+      const escrow = escrowPda(escrowId);
+
+      await expectError(
+        program.methods
+          .initializeEscrow(
+            escrowId,
+            new BN(1_000_000),
+            { sol: {} },
+            arbiter.publicKey,
+            recipient.publicKey,
+            false,
+            new BN(0),
+            false,
+            null,
+            0,
+            null
+          )
+          .accounts({
+            escrow,
+            initiator: initiator.publicKey,
+            tokenOwner: null,
+            config: configPda,
+            userStats: null,
+            template: null,
+            userIndex: null,
+            referralCode: null,
+            recipientWhitelist: recipientWhitelistPda(initiator.publicKey),
+            escrowSolVault: solVaultPda(escrowId),
+            escrowTokenVault: tokenVault(escrowId),
+            initiatorTokenAccount,
+            forgeMint,
+            feeWallet: null,
+            tempFeeWallet: null,
+            feeWalletTokenAccount: null,
+            burnTokenAccount: null,
+            systemProgram: TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          })
+          .signers([initiator])
+          .rpc(),
+        "InvalidProgramId"
+      );
+    });
+  });
+
+  describe("split releases", () => {
+    const splitWallets = (count: number) =>
+      Array.from({ length: count }, () => Keypair.generate().publicKey);
+
+    it("splits a release across the maximum number of recipients", async () => {
+      const { escrowId, escrow } = await createEscrow(100_000_000);
+      const wallets = splitWallets(5);
+
+      await program.methods
+        .setSplitRecipients(wallets.map((wallet) => ({ wallet, bps: 2_000 })))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await program.methods
+        .releaseSplit(100)
+        .accounts(releaseAccounts(escrowId))
+        .remainingAccounts(
+          wallets.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .signers([arbiter])
+        .rpc();
+
+      for (const wallet of wallets) {
+        assert.equal(await balance(wallet), 18_000_000);
+      }
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("rejects more than the maximum number of recipients", async () => {
+      const { escrow } = await createEscrow(100_000_000);
+      const wallets = splitWallets(6);
+
+      await expectError(
+        program.methods
+          .setSplitRecipients(
+            wallets.map((wallet, i) => ({ wallet, bps: i === 0 ? 5_000 : 1_000 }))
+          )
+          .accounts({ escrow, initiator: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "TooManyRecipients"
+      );
+    });
+  });
+
+  describe("acceptance", () => {
+    it("lets the initiator reclaim funds the recipient never accepted", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .setAcceptanceDeadline(new BN((await chainTime()) + 2))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "NotAccepted"
+      );
+      await expectError(
+        program.methods
+          .reclaimUnaccepted()
+          .accounts(cancelAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc(),
+        "AcceptanceWindowOpen"
+      );
+
+      await sleep(4_000);
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .reclaimUnaccepted()
+        .accounts(cancelAccounts(escrowId, initiator.publicKey))
+        .signers([initiator])
+        .rpc();
+
+      assert.isAbove(await balance(initiator.publicKey), initiatorBefore + 990_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+    });
+
+    it("releases once the recipient accepts", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .setAcceptanceDeadline(new BN((await chainTime()) + 60))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await program.methods
+        .acceptEscrow()
+        .accounts({ escrow, recipient: recipient.publicKey, config: configPda })
+        .signers([recipient])
+        .rpc();
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("fee events", () => {
+    it("emits FeeCollected matching the SOL fee movements", async () => {
+      const { escrowId } = await createEscrow(2_000_000);
+
+      const feeBefore = await balance(feeWallet.publicKey);
+      const tempFeeBefore = await balance(tempFeeWallet.publicKey);
+      const signature = await program.methods
+        .releaseFunds(50, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const event = (await eventsOf(signature)).find((e) => e.name === "FeeCollected");
+      assert.exists(event);
+      assert.equal(event.data.version, 1);
+      assert.equal(event.data.escrowId.toNumber(), escrowId.toNumber());
+      assert.isNull(event.data.mint);
+      assert.equal(event.data.treasuryAmount.toNumber(), (await balance(feeWallet.publicKey)) - feeBefore);
+      assert.equal(event.data.burnAmount.toNumber(), (await balance(tempFeeWallet.publicKey)) - tempFeeBefore);
+    });
+
+    it("emits FeeCollected matching the FORGE fee movements", async () => {
+      const { escrowId } = await createEscrow(2_000_000, "forge");
+
+      const feeBefore = await tokenBalance(feeWalletTokenAccount);
+      const burnBefore = await tokenBalance(burnTokenAccount);
+      const signature = await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const event = (await eventsOf(signature)).find((e) => e.name === "FeeCollected");
+      assert.exists(event);
+      assert.equal(event.data.version, 1);
+      assert.isTrue(event.data.mint.equals(forgeMint));
+      assert.equal(event.data.treasuryAmount.toNumber(), (await tokenBalance(feeWalletTokenAccount)) - feeBefore);
+      assert.equal(event.data.burnAmount.toNumber(), (await tokenBalance(burnTokenAccount)) - burnBefore);
+    });
+  });
+
+  describe("governance arbiter", () => {
+    const governance = anchor.workspace.MockGovernance as Program<MockGovernance>;
+    const authority = PublicKey.findProgramAddressSync(
+      [Buffer.from("authority")],
+      governance.programId
+    )[0];
+
+    it("releases when the governance PDA arbiter authorizes via CPI", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", { arbiter: authority });
+
+      const recipientBefore = await balance(recipient.publicKey);
+      await governance.methods
+        .executeRelease(100)
+        .accounts({
+          ...releaseAccounts(escrowId, authority),
+          authority,
+          escrowProgram: program.programId,
+        })
+        .rpc();
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 900_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("pooled escrows", () => {
+    const createPool = async () => {
+      const poolId = new BN(Date.now());
+      const pool = PublicKey.findProgramAddressSync(
+        [Buffer.from("pool"), poolId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+
+      await program.methods
+        .createEscrowPool(poolId)
+        .accounts({ pool, payer: initiator.publicKey, systemProgram: SystemProgram.programId })
+        .signers([initiator])
+        .rpc();
+      return pool;
+    };
+
+    const addPooledEscrow = (pool: anchor.web3.PublicKey, amount: number, escrowArbiter = arbiter.publicKey) =>
+      program.methods
+        .addPooledEscrow(new BN(amount), escrowArbiter, recipient.publicKey)
+        .accounts({
+          pool,
+          initiator: initiator.publicKey,
+          config: configPda,
+          userStats: null,
+          recipientWhitelist: recipientWhitelistPda(initiator.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+
+    it("releases one pooled escrow without touching the others", async () => {
+      const pool = await createPool();
+
+      for (const amount of [100_000, 200_000, 300_000]) {
+        await addPooledEscrow(pool, amount);
+      }
+
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .releasePooledEscrow(1)
+        .accounts({
+          pool,
+          signer: arbiter.publicKey,
+          config: configPda,
+          recipient: recipient.publicKey,
+          feeWallet: feeWallet.publicKey,
+          tempFeeWallet: tempFeeWallet.publicKey,
+        })
+        .signers([arbiter])
+        .rpc();
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 180_000);
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .cancelPooledEscrow(2)
+        .accounts({ pool, signer: arbiter.publicKey, config: configPda, initiator: initiator.publicKey })
+        .signers([arbiter])
+        .rpc();
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 300_000);
+
+      const state = await program.account.escrowPool.fetch(pool);
+      assert.deepEqual(
+        state.entries.map((entry) => entry.status),
+        [{ funded: {} }, { released: {} }, { cancelled: {} }]
+      );
+    });
+
+    it("applies the new-escrow rules to pooled entries", async () => {
+      const pool = await createPool();
+
+      await expectError(addPooledEscrow(pool, 100_000, initiator.publicKey), "SelfArbiter");
+    });
+  });
+
+  describe("deal type validation", () => {
+    it("rejects token accounts passed as the vault of a SOL release", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), escrowSolVault: tokenVault(escrowId) })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+
+    it("releases SOL without any token accounts", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("rejects token accounts passed to a SOL release", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), escrowTokenVault: tokenVault(escrowId) })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+
+    it("rejects SOL accounts passed to a FORGE release", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), escrowSolVault: solVaultPda(escrowId) })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+
+    it("rejects token accounts passed to a SOL cancel", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .cancelEscrow()
+          .accounts({ ...cancelAccounts(escrowId), initiatorTokenAccount, forgeMint })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+
+    it("rejects a foreign token vault on a FORGE cancel", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .cancelEscrow()
+          .accounts({ ...cancelAccounts(escrowId), escrowTokenVault: recipientTokenAccount })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+  });
+
+  describe("treasury config", () => {
+    it("rejects a fee token account that is not the treasury ATA", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+      const nonAtaFeeAccount = await createAccount(
+        connection,
+        payer,
+        forgeMint,
+        feeWallet.publicKey,
+        Keypair.generate()
+      );
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), feeWalletTokenAccount: nonAtaFeeAccount })
+          .signers([arbiter])
+          .rpc(),
+        "ConstraintAssociated"
+      );
+    });
+
+    it("only lets the admin update the treasury", async () => {
+      await expectError(
+        program.methods
+          .updateConfig(initiator.publicKey)
+          .accounts({ config: configPda, admin: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("remaining amount", () => {
+    it("reports the remaining amount net of fees", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .releaseFunds(40, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const gross = await program.methods.getRemainingAmount().accounts({ escrow }).view();
+      const net = await program.methods.getRemainingNet().accounts({ escrow, config: configPda }).view();
+      assert.equal(gross.toNumber(), 600_000);
+      assert.equal(net.toNumber(), gross.toNumber() * 0.9);
+    });
+  });
+
+  describe("permissioned mode", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await program.methods.setPermissioned(true).accounts(adminAccounts()).rpc();
+      await program.methods
+        .addAllowedInitiator(initiator.publicKey)
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods.setPermissioned(false).accounts(adminAccounts()).rpc();
+    });
+
+    it("allows a listed initiator", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.isTrue(state.initiator.equals(initiator.publicKey));
+    });
+
+    it("rejects an unlisted initiator", async () => {
+      await expectError(createEscrow(1_000_000, "sol", { initiator: arbiter }), "InitiatorNotAllowed");
+    });
+  });
+
+  describe("fee at funding", () => {
+    it("takes the fee at funding and releases principal fee-free", async () => {
+      const feeBefore = await tokenBalance(feeWalletTokenAccount);
+      const burnBefore = await tokenBalance(burnTokenAccount);
+
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge", { feeAtFunding: true });
+
+      assert.equal(await tokenBalance(feeWalletTokenAccount), feeBefore + 50_000);
+      assert.equal(await tokenBalance(burnTokenAccount), burnBefore + 50_000);
+      assert.equal(await tokenBalance(tokenVault(escrowId)), 900_000);
+      let state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 900_000);
+
+      const recipientBefore = await tokenBalance(recipientTokenAccount);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await tokenBalance(recipientTokenAccount), recipientBefore + 900_000);
+      assert.equal(await tokenBalance(feeWalletTokenAccount), feeBefore + 50_000);
+      assert.equal(await tokenBalance(burnTokenAccount), burnBefore + 50_000);
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("never withdraws more than the vault holds across partial releases", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", { feeAtFunding: true });
+      const recipientBefore = await balance(recipient.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+
+      for (const percentage of [30, 50, 100]) {
+        await program.methods
+          .releaseFunds(percentage, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+      }
+
+      // Only the 900_000 principal went in, and exactly that came out, fee-free
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 900_000);
+      assert.equal(await balance(feeWallet.publicKey), treasuryBefore);
+      assert.equal(
+        await balance(solVaultPda(escrowId)),
+        await connection.getMinimumBalanceForRentExemption(0)
+      );
+    });
+  });
+
+  describe("contributions", () => {
+    const sponsorA = Keypair.generate();
+    const sponsorB = Keypair.generate();
+
+    before(async () => {
+      await airdrop(sponsorA.publicKey);
+      await airdrop(sponsorB.publicKey);
+    });
+
+    const contribute = (escrowId: BN, sponsor: anchor.web3.Keypair, amount: number) =>
+      program.methods
+        .contribute(new BN(amount))
+        .accounts({
+          escrow: escrowPda(escrowId),
+          contributor: sponsor.publicKey,
+          config: configPda,
+          userStats: null,
+          escrowSolVault: solVaultPda(escrowId),
+          escrowTokenVault: tokenVault(escrowId),
+          contributorTokenAccount: initiatorTokenAccount,
+          forgeMint,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([sponsor])
+        .rpc();
+
+    it("refunds two contributors proportionally on cancel", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await contribute(escrowId, sponsorA, 1_000_000);
+      await contribute(escrowId, sponsorB, 2_000_000);
+
+      let state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 4_000_000);
+
+      // Half goes to the recipient first, so each party gets half their stake back
+      await program.methods
+        .releaseFunds(50, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      const sponsorABefore = await balance(sponsorA.publicKey);
+      const sponsorBBefore = await balance(sponsorB.publicKey);
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .remainingAccounts([
+          { pubkey: sponsorA.publicKey, isWritable: true, isSigner: false },
+          { pubkey: sponsorB.publicKey, isWritable: true, isSigner: false },
+        ])
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 500_000);
+      assert.equal(await balance(sponsorA.publicKey), sponsorABefore + 500_000);
+      assert.equal(await balance(sponsorB.publicKey), sponsorBBefore + 1_000_000);
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+    });
+
+    it("refunds contributors proportionally on a partial cancel", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await contribute(escrowId, sponsorA, 1_000_000);
+      await contribute(escrowId, sponsorB, 2_000_000);
+      const payees = [
+        { pubkey: sponsorA.publicKey, isWritable: true, isSigner: false },
+        { pubkey: sponsorB.publicKey, isWritable: true, isSigner: false },
+      ];
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      const sponsorABefore = await balance(sponsorA.publicKey);
+      const sponsorBBefore = await balance(sponsorB.publicKey);
+      await program.methods
+        .partialCancel(25)
+        .accounts(cancelAccounts(escrowId))
+        .remainingAccounts(payees)
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 250_000);
+      assert.equal(await balance(sponsorA.publicKey), sponsorABefore + 250_000);
+      assert.equal(await balance(sponsorB.publicKey), sponsorBBefore + 500_000);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 3_000_000);
+      assert.deepEqual(
+        state.contributions.map((c) => c.amount.toNumber()),
+        [750_000, 1_500_000]
+      );
+      assert.deepEqual(state.status, { funded: {} });
+    });
+
+    it("lets the initiator withdraw an overfunded top-up but not contributors' funds", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await contribute(escrowId, initiator, 500_000);
+      await contribute(escrowId, sponsorA, 1_000_000);
+
+      const withdraw = (amount: number) =>
+        program.methods
+          .withdrawOverfund(new BN(amount))
+          .accounts(cancelAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc();
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      await withdraw(500_000);
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 500_000);
+
+      let state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 2_000_000);
+      assert.deepEqual(
+        state.contributions.map((c) => c.contributor.toBase58()),
+        [sponsorA.publicKey.toBase58()]
+      );
+
+      await expectError(withdraw(1_000_000), "WithdrawExceedsOverfund");
+      state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 2_000_000);
+    });
+
+    it("rejects contributions while the escrow is paused", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .pauseEscrow()
+        .accounts({ escrow, party: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+
+      await expectError(contribute(escrowId, sponsorA, 1_000_000), "EscrowPaused");
+    });
+
+    it("rejects milestones once contributions have been accepted", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await contribute(escrowId, sponsorA, 1_000_000);
+
+      await expectError(
+        program.methods
+          .setMilestones([new BN(1_000_000), new BN(1_000_000)])
+          .accounts({ escrow, initiator: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "InvalidContributor"
+      );
+    });
+  });
+
+  describe("vault balance checks", () => {
+    it("rejects a token release the vault cannot cover", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+
+      // An escrow-owned token account that holds nothing
+      const otherMint = await createMint(connection, payer, payer.publicKey, null, 6);
+      const emptyVault = (
+        await getOrCreateAssociatedTokenAccount(connection, payer, otherMint, escrow, true)
+      ).address;
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), escrowTokenVault: emptyVault })
+          .signers([arbiter])
+          .rpc(),
+        "InsufficientFunds"
+      );
+    });
+  });
+
+  describe("settlement", () => {
+    it("pays 40% to the recipient and refunds 60% in one instruction", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      const recipientBefore = await balance(recipient.publicKey);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .settle(4_000)
+        .accounts(settleAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 360_000);
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 600_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 400_000);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("only lets the arbiter settle", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .settle(4_000)
+          .accounts(settleAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("finalizes with a fixed recipient payment and refunds the remainder", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(10, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const recipientBefore = await balance(recipient.publicKey);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .finalize(new BN(300_000))
+        .accounts(settleAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      // 900_000 was left: 300_000 to the recipient less the 10% fee, 600_000 back
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 270_000);
+      assert.equal(await balance(initiator.publicKey), initiatorBefore + 600_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 400_000);
+      assert.deepEqual(state.status, { released: {} });
+
+      await expectError(
+        program.methods
+          .finalize(new BN(1))
+          .accounts(settleAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "InvalidEscrowStatus"
+      );
+    });
+
+    it("rejects finalizing for more than is left", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .finalize(new BN(1_000_001))
+          .accounts(settleAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "InsufficientFunds"
+      );
+    });
+  });
+
+  describe("compute budget", () => {
+    const computeUnits = async (signature: string) => {
+      await connection.confirmTransaction(signature, "confirmed");
+      const tx = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return tx?.meta?.computeUnitsConsumed ?? 0;
+    };
+
+    const constant = (name: string) =>
+      Number(program.idl.constants.find((c) => c.name === name).value.replace(/_/g, ""));
+
+    it("keeps a maximum-size FORGE split release within the recommended budget", async () => {
+      const { escrowId, escrow } = await createEscrow(10_000_000, "forge");
+      const wallets = Array.from({ length: 5 }, () => Keypair.generate());
+      const payees = [];
+      for (const wallet of wallets) {
+        payees.push(
+          await createAssociatedTokenAccount(connection, payer, forgeMint, wallet.publicKey)
+        );
+      }
+
+      await program.methods
+        .setSplitRecipients(wallets.map((w) => ({ wallet: w.publicKey, bps: 2_000 })))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      const limit = constant("RELEASE_SPLIT_COMPUTE_UNITS");
+      const signature = await program.methods
+        .releaseSplit(100)
+        .accounts(releaseAccounts(escrowId))
+        .remainingAccounts(payees.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false })))
+        .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: limit })])
+        .signers([arbiter])
+        .rpc();
+
+      const used = await computeUnits(signature);
+      console.log(`release_split (5 FORGE recipients): ${used} CU`);
+      assert.isAtMost(used, limit);
+    });
+
+    it("keeps FORGE initialization within the recommended budget", async () => {
+      const escrowId = new BN(nextEscrowId);
+      await createEscrow(1_000_000, "forge");
+
+      const [signature] = await connection.getSignaturesForAddress(escrowPda(escrowId), {}, "confirmed");
+      const used = await computeUnits(signature.signature);
+      console.log(`initialize_escrow (FORGE): ${used} CU`);
+      assert.isAtMost(used, constant("INITIALIZE_ESCROW_COMPUTE_UNITS"));
+    });
+  });
+
+  describe("partially released escrows", () => {
+    it("cancels a partially released escrow refunding only the remainder", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+
+      await program.methods
+        .releaseFunds(30, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      let state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { partiallyReleased: {} });
+
+      const initiatorBefore = await tokenBalance(initiatorTokenAccount);
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await tokenBalance(initiatorTokenAccount), initiatorBefore + 700_000);
+      assert.equal(await tokenBalance(tokenVault(escrowId)), 0);
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+    });
+  });
+
+  describe("burn account validation", () => {
+    it("rejects a burn account for a different mint", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+      const otherMint = await createMint(connection, payer, payer.publicKey, null, 6);
+      const wrongMintBurn = await createAssociatedTokenAccount(
+        connection,
+        payer,
+        otherMint,
+        burnWallet.publicKey
+      );
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), burnTokenAccount: wrongMintBurn })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidBurnAddress"
+      );
+    });
+  });
+
+  describe("scheduled release", () => {
+    const keeper = Keypair.generate();
+
+    before(async () => {
+      await airdrop(keeper.publicKey);
+    });
+
+    it("lets a keeper release only after the scheduled time", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .scheduleRelease(new BN((await chainTime()) + 2))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .executeScheduledRelease()
+          .accounts(releaseAccounts(escrowId, keeper.publicKey))
+          .signers([keeper])
+          .rpc(),
+        "ReleaseNotDue"
+      );
+
+      await sleep(4_000);
+
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .executeScheduledRelease()
+        .accounts(releaseAccounts(escrowId, keeper.publicKey))
+        .signers([keeper])
+        .rpc();
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 900_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("needs both the scheduled time and the arbiter when require_both is on", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .scheduleRelease(new BN((await chainTime()) + 2))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await program.methods
+        .enableRequireBoth()
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      const release = (signer: anchor.web3.Keypair) =>
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId, signer.publicKey))
+          .signers([signer])
+          .rpc();
+
+      // Approval alone, on any payout path
+      await expectError(release(arbiter), "ReleaseNotDue");
+      await expectError(
+        program.methods
+          .settle(10_000)
+          .accounts(settleAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "ReleaseNotDue"
+      );
+
+      await sleep(4_000);
+
+      // Time alone
+      await expectError(release(initiator), "ArbiterApprovalRequired");
+      await expectError(
+        program.methods
+          .executeScheduledRelease()
+          .accounts(releaseAccounts(escrowId, keeper.publicKey))
+          .signers([keeper])
+          .rpc(),
+        "ArbiterApprovalRequired"
+      );
+
+      await release(arbiter);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("fee wallet validation", () => {
+    it("rejects the fee account doubling as the burn account", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), burnTokenAccount: feeWalletTokenAccount })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidFeeWallet"
+      );
+    });
+  });
+
+  describe("USD-denominated escrow", () => {
+    const mockPyth = anchor.workspace.MockPyth as Program<MockPyth>;
+    const priceFeed = Keypair.generate();
+    const PRICE_ACCOUNT_SIZE = 3312;
+
+    const setSolPrice = async (usd: number) =>
+      mockPyth.methods
+        .setPrice(new BN(usd * 100_000_000), -8, new BN(await chainTime()))
+        .accounts({ priceFeed: priceFeed.publicKey })
+        .rpc();
+
+    const releaseUsd = (escrowId: BN, percentage: number) =>
+      program.methods
+        .releaseUsdEscrow(percentage)
+        .accounts(releaseAccounts(escrowId))
+        .remainingAccounts([
+          { pubkey: priceFeed.publicKey, isWritable: false, isSigner: false },
+        ])
+        .signers([arbiter])
+        .rpc();
+
+    before(async () => {
+      const tx = new anchor.web3.Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: priceFeed.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(PRICE_ACCOUNT_SIZE),
+          space: PRICE_ACCOUNT_SIZE,
+          programId: mockPyth.programId,
+        })
+      );
+      await provider.sendAndConfirm(tx, [priceFeed]);
+      await program.methods
+        .setPriceFeed(priceFeed.publicKey)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    it("pays the SOL equivalent at the current price, capped by the vault", async () => {
+      const { escrowId, escrow } = await createEscrow(2 * LAMPORTS_PER_SOL);
+      await program.methods
+        .setUsdAmount(new BN(100_000_000)) // $100
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      // $50 at $100/SOL is 0.5 SOL, 10% fee withheld
+      await setSolPrice(100);
+      let before = await balance(recipient.publicKey);
+      await releaseUsd(escrowId, 50);
+      assert.equal((await balance(recipient.publicKey)) - before, 0.45 * LAMPORTS_PER_SOL);
+
+      // The last $50 at $25/SOL implies 2 SOL, but only 1.5 SOL is left
+      await setSolPrice(25);
+      before = await balance(recipient.publicKey);
+      await releaseUsd(escrowId, 100);
+      assert.equal((await balance(recipient.publicKey)) - before, 1.35 * LAMPORTS_PER_SOL);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+      assert.equal(state.releasedAmount.toNumber(), 2 * LAMPORTS_PER_SOL);
+    });
+
+    it("rejects lamport-based releases of a USD escrow", async () => {
+      const { escrowId, escrow } = await createEscrow(LAMPORTS_PER_SOL);
+      await program.methods
+        .setUsdAmount(new BN(10_000_000))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "UsdEscrow"
+      );
+      await expectError(
+        program.methods
+          .setMilestones([new BN(LAMPORTS_PER_SOL)])
+          .accounts({ escrow, initiator: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "UsdEscrow"
+      );
+    });
+  });
+
+  describe("admin handoff", () => {
+    const newAdmin = Keypair.generate();
+
+    it("keeps the current admin until the proposal is accepted", async () => {
+      await program.methods
+        .proposeAdmin(newAdmin.publicKey)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+
+      let config = await program.account.config.fetch(configPda);
+      assert.ok(config.admin.equals(payer.publicKey));
+      assert.ok(config.pendingAdmin.equals(newAdmin.publicKey));
+
+      await expectError(
+        program.methods
+          .acceptAdmin()
+          .accounts({ config: configPda, newAdmin: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+      config = await program.account.config.fetch(configPda);
+      assert.ok(config.admin.equals(payer.publicKey));
+    });
+
+    it("hands over once the proposed admin accepts", async () => {
+      await program.methods
+        .acceptAdmin()
+        .accounts({ config: configPda, newAdmin: newAdmin.publicKey })
+        .signers([newAdmin])
+        .rpc();
+
+      const config = await program.account.config.fetch(configPda);
+      assert.ok(config.admin.equals(newAdmin.publicKey));
+      assert.isNull(config.pendingAdmin);
+
+      // Hand back so later suites can keep administering the config
+      await program.methods
+        .proposeAdmin(payer.publicKey)
+        .accounts({ config: configPda, admin: newAdmin.publicKey })
+        .signers([newAdmin])
+        .rpc();
+      await program.methods
+        .acceptAdmin()
+        .accounts({ config: configPda, newAdmin: payer.publicKey })
+        .rpc();
+    });
+  });
+
+  describe("display info", () => {
+    it("returns the deal type, amount, decimals and label of the escrow", async () => {
+      const sol = await createEscrow(1_500_000);
+      const solInfo = await program.methods
+        .getDisplayInfo()
+        .accounts({ escrow: sol.escrow, mint: null })
+        .view();
+      assert.deepEqual(solInfo.dealType, { sol: {} });
+      assert.equal(solInfo.amount.toNumber(), 1_500_000);
+      assert.equal(solInfo.decimals, 9);
+      assert.equal(solInfo.label, "SOL");
+
+      const forge = await createEscrow(2_000_000, "forge");
+      const forgeInfo = await program.methods
+        .getDisplayInfo()
+        .accounts({ escrow: forge.escrow, mint: forgeMint })
+        .view();
+      assert.deepEqual(forgeInfo.dealType, { forge: {} });
+      assert.equal(forgeInfo.amount.toNumber(), 2_000_000);
+      assert.equal(forgeInfo.decimals, 6);
+      assert.equal(forgeInfo.label, "FORGE");
+    });
+  });
+
+  describe("expiry refunds", () => {
+    const claimExpired = (escrowId: BN) =>
+      program.methods
+        .claimExpired()
+        .accounts({
+          ...releaseAccounts(escrowId, initiator.publicKey),
+          recipient: initiator.publicKey,
+        })
+        .signers([initiator])
+        .rpc();
+
+    it("refunds the full principal when expiry does not charge a fee", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", {
+        expiresAt: (await chainTime()) + 2,
+      });
+      await expectError(claimExpired(escrowId), "NotExpired");
+      await sleep(4_000);
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await claimExpired(escrowId);
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, 1_000_000);
+      assert.equal(await balance(feeWallet.publicKey), treasuryBefore);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+    });
+
+    it("routes the refund through the fee split when expiry charges a fee", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", {
+        expiresAt: (await chainTime()) + 2,
+        expiryChargesFee: true,
+      });
+      await sleep(4_000);
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await claimExpired(escrowId);
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, 900_000);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 50_000);
+    });
+  });
+
+  describe("labels", () => {
+    it("grows an existing escrow to store a label, charging the initiator", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+      const sizeBefore = (await connection.getAccountInfo(escrow)).data.length;
+      const initiatorBefore = await balance(initiator.publicKey);
+
+      const label = "Website redesign";
+      await program.methods
+        .addLabel(label)
+        .accounts({
+          escrow,
+          initiator: initiator.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+
+      const info = await connection.getAccountInfo(escrow);
+      assert.equal(info.data.length, sizeBefore + label.length);
+      assert.isBelow(await balance(initiator.publicKey), initiatorBefore);
+      assert.equal((await program.account.escrow.fetch(escrow)).label, label);
+    });
+
+    it("rejects labels over the maximum length", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .addLabel("x".repeat(65))
+          .accounts({
+            escrow,
+            initiator: initiator.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([initiator])
+          .rpc(),
+        "LabelTooLong"
+      );
+    });
+
+    it("lets both parties correct the metadata before release, keeping a history", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const updateMetadata = (label: string, termsHash: Buffer) =>
+        program.methods
+          .updateMetadata(label, Array.from(termsHash))
+          .accounts({
+            escrow,
+            initiator: initiator.publicKey,
+            recipient: recipient.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([initiator, recipient])
+          .rpc();
+      const metadataHash = (label: string, termsHash: Buffer) =>
+        createHash("sha256").update(Buffer.from(label)).update(termsHash).digest();
+
+      const firstTerms = createHash("sha256").update("terms v1").digest();
+      const secondTerms = createHash("sha256").update("terms v2").digest();
+      await updateMetadata("Logo design", firstTerms);
+      await updateMetadata("Logo and brand guide", secondTerms);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.label, "Logo and brand guide");
+      assert.deepEqual(Buffer.from(state.termsUriHash), secondTerms);
+      assert.deepEqual(
+        state.metadataHistory.map((entry) => Buffer.from(entry)),
+        [metadataHash("", Buffer.alloc(32)), metadataHash("Logo design", firstTerms)]
+      );
+
+      await program.methods
+        .releaseFunds(10, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      await expectError(updateMetadata("Too late", firstTerms), "MetadataLocked");
+    });
+  });
+
+  describe("referrers", () => {
+    const referrer = Keypair.generate();
+
+    before(async () => {
+      await airdrop(referrer.publicKey);
+    });
+
+    it("pays the referrer its cut of the fee before the treasury/burn split", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", {
+        referrer: referrer.publicKey,
+        referrerBps: 2_000, // 20% of the 100_000 fee
+      });
+
+      const referrerBefore = await balance(referrer.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      const tempBefore = await balance(tempFeeWallet.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({ ...releaseAccounts(escrowId), referrer: referrer.publicKey })
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(referrer.publicKey)) - referrerBefore, 20_000);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 40_000);
+      assert.equal((await balance(tempFeeWallet.publicKey)) - tempBefore, 40_000);
+    });
+
+    it("pays the referrer its cut of a settlement fee", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", {
+        referrer: referrer.publicKey,
+        referrerBps: 2_000, // 20% of the 40_000 fee on the recipient's share
+      });
+
+      const referrerBefore = await balance(referrer.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await program.methods
+        .settle(4_000)
+        .accounts({ ...settleAccounts(escrowId), referrer: referrer.publicKey })
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(referrer.publicKey)) - referrerBefore, 8_000);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 16_000);
+    });
+
+    it("rejects a release that pays the wrong referrer", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", {
+        referrer: referrer.publicKey,
+        referrerBps: 2_000,
+      });
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), referrer: recipient.publicKey })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidReferrer"
+      );
+    });
+
+    it("credits the referrer a registered referral code resolves to", async () => {
+      const code = `ref${nextEscrowId}`;
+      const [referralCode] = PublicKey.findProgramAddressSync(
+        [Buffer.from("referral_code"), Buffer.from(code)],
+        program.programId
+      );
+      await program.methods
+        .registerReferralCode(code)
+        .accounts({ referralCode, referrer: referrer.publicKey })
+        .signers([referrer])
+        .rpc();
+
+      await expectError(
+        createEscrow(1_000_000, "sol", {
+          referralCode,
+          referrer: recipient.publicKey,
+          referrerBps: 2_000,
+        }),
+        "InvalidReferrer"
+      );
+
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", {
+        referralCode,
+        referrerBps: 2_000,
+      });
+      assert.ok((await program.account.escrow.fetch(escrow)).referrer.equals(referrer.publicKey));
+
+      const referrerBefore = await balance(referrer.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({ ...releaseAccounts(escrowId), referrer: referrer.publicKey })
+        .signers([arbiter])
+        .rpc();
+      assert.equal((await balance(referrer.publicKey)) - referrerBefore, 20_000);
+    });
+  });
+
+  describe("token vault reuse", () => {
+    it("rejects an escrow whose vault ATA was pre-funded", async () => {
+      const escrowId = new BN(nextEscrowId);
+      const vault = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          payer,
+          forgeMint,
+          escrowPda(escrowId),
+          true
+        )
+      ).address;
+      await mintTo(connection, payer, forgeMint, vault, payer, 1_000);
+
+      await expectError(createEscrow(1_000_000, "forge"), "VaultNotEmpty");
+    });
+  });
+
+  describe("recipient override", () => {
+    const appointed = Keypair.generate();
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await airdrop(appointed.publicKey);
+    });
+
+    it("lets the arbiter pay an override recipient", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      const appointedBefore = await balance(appointed.publicKey);
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .releaseFunds(50, null, appointed.publicKey, null)
+        .accounts({ ...releaseAccounts(escrowId), recipient: appointed.publicKey })
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(appointed.publicKey)) - appointedBefore, 450_000);
+      assert.equal(await balance(recipient.publicKey), recipientBefore);
+    });
+
+    it("rejects overrides by the initiator or outside the allowlist", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, appointed.publicKey, null)
+          .accounts({
+            ...releaseAccounts(escrowId, initiator.publicKey),
+            recipient: appointed.publicKey,
+          })
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+
+      await program.methods
+        .setOverrideAllowlist([tempFeeWallet.publicKey])
+        .accounts(adminAccounts())
+        .rpc();
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, appointed.publicKey, null)
+          .accounts({ ...releaseAccounts(escrowId), recipient: appointed.publicKey })
+          .signers([arbiter])
+          .rpc(),
+        "InvalidOverrideRecipient"
+      );
+      await program.methods.setOverrideAllowlist([]).accounts(adminAccounts()).rpc();
+    });
+
+    it("only pays another wallet through a validated override", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      for (const signer of [arbiter, initiator]) {
+        await expectError(
+          program.methods
+            .releaseFunds(50, null, null, null)
+            .accounts({
+              ...releaseAccounts(escrowId, signer.publicKey),
+              recipient: appointed.publicKey,
+            })
+            .signers([signer])
+            .rpc(),
+          "Unauthorized"
+        );
+      }
+    });
+  });
+
+  describe("release result", () => {
+    it("returns the amounts moved, matching the FeeCollected event", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      const signature = await program.methods
+        .releaseFunds(40, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc({ commitment: "confirmed" });
+
+      const tx = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const prefix = `Program return: ${program.programId} `;
+      const returnLog = tx.meta.logMessages.find((log) => log.startsWith(prefix));
+      const result = program.coder.types.decode(
+        "ReleaseResult",
+        Buffer.from(returnLog.slice(prefix.length), "base64")
+      );
+
+      assert.equal(result.gross.toNumber(), 400_000);
+      assert.equal(result.net.toNumber(), 360_000);
+      assert.deepEqual(result.newStatus, { partiallyReleased: {} });
+
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "FeeCollected");
+      assert.equal(result.fee.toNumber(), event.data.treasuryAmount.toNumber());
+      assert.equal(result.burn.toNumber(), event.data.burnAmount.toNumber());
+    });
+  });
+
+  describe("pausing", () => {
+    const pauseAccounts = (escrow: anchor.web3.PublicKey, party: anchor.web3.Keypair) => ({
+      escrow,
+      party: party.publicKey,
+    });
+
+    it("lets the arbiter settle a paused escrow it cannot unpause", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .pauseEscrow()
+        .accounts(pauseAccounts(escrow, recipient))
+        .signers([recipient])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .unpauseEscrow()
+          .accounts(pauseAccounts(escrow, arbiter))
+          .signers([arbiter])
+          .rpc(),
+        "Unauthorized"
+      );
+
+      await program.methods
+        .settle(4_000)
+        .accounts(settleAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.deepEqual((await program.account.escrow.fetch(escrow)).status, { released: {} });
+    });
+
+    it("blocks releases until the pausing party unpauses", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .pauseEscrow()
+        .accounts(pauseAccounts(escrow, recipient))
+        .signers([recipient])
+        .rpc();
+      assert.ok((await program.account.escrow.fetch(escrow)).pausedBy.equals(recipient.publicKey));
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "EscrowPaused"
+      );
+      await expectError(
+        program.methods
+          .unpauseEscrow()
+          .accounts(pauseAccounts(escrow, initiator))
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+
+      await program.methods
+        .unpauseEscrow()
+        .accounts(pauseAccounts(escrow, recipient))
+        .signers([recipient])
+        .rpc();
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("sol vault balance", () => {
+    it("refunds lamports sent to the vault outside the program on cancel", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const vault = solVaultPda(escrowId);
+
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer.publicKey,
+            toPubkey: vault,
+            lamports: 500_000,
+          })
+        )
+      );
+
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, 1_500_000);
+      assert.equal(await balance(vault), await connection.getMinimumBalanceForRentExemption(0));
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+    });
+  });
+
+  describe("closing the sol vault", () => {
+    const closeVault = (escrowId: BN) =>
+      program.methods
+        .closeSolVault()
+        .accounts({
+          escrow: escrowPda(escrowId),
+          initiator: initiator.publicKey,
+          escrowSolVault: solVaultPda(escrowId),
+          config: configPda,
+          treasury: feeWallet.publicKey,
+        })
+        .signers([initiator])
+        .rpc();
+
+    it("returns the vault rent to the initiator after a cancel", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const vault = solVaultPda(escrowId);
+
+      await expectError(closeVault(escrowId), "InvalidEscrowStatus");
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const rent = await balance(vault);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await closeVault(escrowId);
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, rent);
+      assert.isNull(await connection.getAccountInfo(vault));
+      assert.isNotNull(await connection.getAccountInfo(escrow));
+    });
+
+    it("routes dust above the vault rent to the treasury", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const vault = solVaultPda(escrowId);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: vault, lamports: 1_234 })
+        )
+      );
+
+      const rent = await connection.getMinimumBalanceForRentExemption(0);
+      assert.equal(await balance(vault), rent + 1_234);
+      const initiatorBefore = await balance(initiator.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await closeVault(escrowId);
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, rent);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 1_234);
+      assert.isNull(await connection.getAccountInfo(vault));
+    });
+  });
+
+  describe("sweeping stranded vaults", () => {
+    const sweep = (escrowId: BN, signer = payer, escrowInitiator = initiator.publicKey) =>
+      program.methods
+        .sweepStrandedVault(escrowId)
+        .accounts({
+          config: configPda,
+          admin: signer.publicKey,
+          escrow: escrowPda(escrowId),
+          initiator: escrowInitiator,
+          escrowSolVault: solVaultPda(escrowId),
+          treasury: feeWallet.publicKey,
+        })
+        .signers(signer === payer ? [] : [signer])
+        .rpc();
+
+    it("closes a finished escrow's leftover vault and returns its rent to the initiator", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await expectError(sweep(escrowId), "InvalidEscrowStatus");
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      await expectError(sweep(escrowId, arbiter), "Unauthorized");
+
+      const vault = solVaultPda(escrowId);
+      const rent = await balance(vault);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await sweep(escrowId);
+
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, rent);
+      assert.isNull(await connection.getAccountInfo(vault));
+      assert.isNotNull(await connection.getAccountInfo(escrow));
+      await expectError(sweep(escrowId), "VaultAlreadyClosed");
+    });
+
+    it("sweeps the vault of an escrow still in the original layout", async () => {
+      // Preloaded from tests/fixtures: a released 131-byte escrow and its vault holding
+      // rent plus 1_234 lamports of dust
+      const escrowId = new BN(1_000_000_000);
+      const legacyInitiator = new PublicKey("Hcun2aVv5uA8FvCTA13UUQPa8f5hzGKun1LbtAnVAuMo");
+      const escrow = await connection.getAccountInfo(escrowPda(escrowId));
+      assert.equal(escrow.data.length, 131);
+
+      await expectError(sweep(escrowId), "Unauthorized");
+
+      const vault = solVaultPda(escrowId);
+      const vaultBalance = await balance(vault);
+      const initiatorBefore = await balance(legacyInitiator);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await sweep(escrowId, payer, legacyInitiator);
+
+      assert.equal((await balance(legacyInitiator)) - initiatorBefore, vaultBalance - 1_234);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 1_234);
+      assert.isNull(await connection.getAccountInfo(vault));
+    });
+  });
+
+  describe("milestone disputes", () => {
+    it("adjudicates a disputed milestone while the others release normally", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .setMilestones([new BN(400_000), new BN(600_000)])
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await program.methods
+        .disputeMilestone(1)
+        .accounts({ escrow, party: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+      // The initiator can't cancel the disputed milestone's funds out from under the dispute
+      await expectError(
+        program.methods
+          .cancelEscrow()
+          .accounts(cancelAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc(),
+        "EscrowDisputed"
+      );
+      await expectError(
+        program.methods
+          .releaseMilestone(1)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "InvalidMilestoneStatus"
+      );
+
+      await program.methods
+        .releaseMilestone(0)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      let state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { partiallyReleased: {} });
+
+      const recipientBefore = await balance(recipient.publicKey);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .resolveMilestoneDispute(1, 5_000)
+        .accounts(settleAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(recipient.publicKey)) - recipientBefore, 270_000);
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, 300_000);
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.milestones[1].status, { released: {} });
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("percentage validation", () => {
+    it("distinguishes a zero percentage from one above 100", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const release = (percentage: number) =>
+        program.methods
+          .releaseFunds(percentage, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+
+      await expectError(release(0), "PercentageIsZero");
+      await expectError(release(101), "PercentageTooHigh");
+    });
+  });
+
+  describe("fee tiers", () => {
+    const whale = Keypair.generate();
+    const whaleStats = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stats"), whale.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await airdrop(whale.publicKey);
+      await program.methods
+        .initializeUserStats()
+        .accounts({
+          userStats: whaleStats,
+          user: whale.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([whale])
+        .rpc();
+      await program.methods
+        .setFeeTiers([{ dealType: { sol: {} }, minVolume: new BN(1_000_000), feeBps: 500 }])
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods.setFeeTiers([]).accounts(adminAccounts()).rpc();
+      await program.methods.setMaxAllowedFeeBps(1_000).accounts(adminAccounts()).rpc();
+    });
+
+    it("charges a reduced fee once the initiator passes a volume threshold", async () => {
+      const releaseAll = async (escrowId: BN) => {
+        const before = await balance(recipient.publicKey);
+        await program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), userStats: whaleStats })
+          .signers([arbiter])
+          .rpc();
+        return (await balance(recipient.publicKey)) - before;
+      };
+
+      const first = await createEscrow(1_000_000, "sol", { initiator: whale });
+      assert.equal(await releaseAll(first.escrowId), 900_000);
+
+      const stats = await program.account.userStats.fetch(whaleStats);
+      assert.equal(stats.solVolume.toNumber(), 1_000_000);
+
+      const second = await createEscrow(1_000_000, "sol", { initiator: whale });
+      assert.equal(await releaseAll(second.escrowId), 950_000);
+    });
+
+    it("rejects tiers above the governance fee cap", async () => {
+      const tier = (feeBps: number) => [{ dealType: { sol: {} }, minVolume: new BN(1_000_000), feeBps }];
+      await expectError(
+        program.methods.setMaxAllowedFeeBps(1_001).accounts(adminAccounts()).rpc(),
+        "FeeTooHigh"
+      );
+      await program.methods.setMaxAllowedFeeBps(400).accounts(adminAccounts()).rpc();
+
+      await program.methods.setFeeTiers(tier(400)).accounts(adminAccounts()).rpc();
+      await expectError(
+        program.methods.setFeeTiers(tier(401)).accounts(adminAccounts()).rpc(),
+        "FeeTooHigh"
+      );
+      assert.equal((await program.account.config.fetch(configPda)).feeTiers[0].feeBps, 400);
+    });
+
+    it("clamps the standard release fee to the governance cap, down to zero", async () => {
+      const releaseAll = async () => {
+        const { escrowId } = await createEscrow(1_000_000, "sol");
+        const before = await balance(recipient.publicKey);
+        await program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+        return (await balance(recipient.publicKey)) - before;
+      };
+
+      await program.methods.setMaxAllowedFeeBps(400).accounts(adminAccounts()).rpc();
+      assert.equal(await releaseAll(), 960_000);
+
+      await program.methods.setMaxAllowedFeeBps(0).accounts(adminAccounts()).rpc();
+      assert.equal(await releaseAll(), 1_000_000);
+    });
+  });
+
+  describe("multisig recipient", () => {
+    const mockMultisig = anchor.workspace.MockMultisig as Program<MockMultisig>;
+    const memberA = Keypair.generate();
+    const memberB = Keypair.generate();
+    const vault = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), memberA.publicKey.toBuffer(), memberB.publicKey.toBuffer()],
+      mockMultisig.programId
+    )[0];
+
+    it("accepts through the multisig vault PDA via CPI", async () => {
+      const { escrow } = await createEscrow(1_000_000, "sol", { recipient: vault });
+      await program.methods
+        .setAcceptanceDeadline(new BN((await chainTime()) + 60))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await mockMultisig.methods
+        .executeAccept()
+        .accounts({
+          memberA: memberA.publicKey,
+          memberB: memberB.publicKey,
+          vault,
+          escrow,
+          escrowProgram: program.programId,
+        })
+        .signers([memberA, memberB])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.isTrue(state.accepted);
+    });
+  });
+
+  describe("schema version", () => {
+    it("stamps new escrows with the current schema version", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+
+      const details = await program.methods.getEscrowDetails().accounts({ escrow }).view();
+      const version = program.idl.constants.find((c) => c.name === "ESCROW_SCHEMA_VERSION");
+      assert.equal(details.schemaVersion, Number(version.value));
+      assert.equal(details.schemaVersion, 1);
+      assert.equal(details.amount.toNumber(), 1_000_000);
+    });
+  });
+
+  describe("arbiter gas rebate", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await program.methods.setArbiterGasRebate(new BN(50_000)).accounts(adminAccounts()).rpc();
+    });
+
+    after(async () => {
+      await program.methods.setArbiterGasRebate(new BN(0)).accounts(adminAccounts()).rpc();
+    });
+
+    it("reimburses the arbiter when it cancels", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      const arbiterBefore = await balance(arbiter.publicKey);
+      const initiatorBefore = await balance(initiator.publicKey);
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(arbiter.publicKey)) - arbiterBefore, 50_000);
+      assert.equal((await balance(initiator.publicKey)) - initiatorBefore, 950_000);
+    });
+
+    it("caps the rebate by the configured maximum and the remaining funds", async () => {
+      await expectError(
+        program.methods.setArbiterGasRebate(new BN(100_001)).accounts(adminAccounts()).rpc(),
+        "GasRebateTooHigh"
+      );
+
+      const { escrowId } = await createEscrow(30_000);
+      const arbiterBefore = await balance(arbiter.publicKey);
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal((await balance(arbiter.publicKey)) - arbiterBefore, 30_000);
+    });
+  });
+
+  describe("release accounting invariant", () => {
+    // Small deterministic PRNG so failures are reproducible
+    const prng = (seed: number) => () => {
+      seed = (seed * 1_103_515_245 + 12_345) % 2 ** 31;
+      return seed / 2 ** 31;
+    };
+
+    for (const seed of [1, 7, 42, 2024]) {
+      it(`delivers the whole deposit across random release sequences (seed ${seed})`, async () => {
+        const random = prng(seed);
+        const deposit = 1_000_003 + Math.floor(random() * 10_000);
+        const { escrowId } = await createEscrow(deposit);
+        const wallets = [recipient.publicKey, feeWallet.publicKey, tempFeeWallet.publicKey];
+        const before = await Promise.all(wallets.map(balance));
+
+        // Percentages of the remaining amount, ending with 100 to drain the escrow
+        const steps = Array.from({ length: 1 + Math.floor(random() * 4) }, () =>
+          1 + Math.floor(random() * 99)
+        );
+        for (const percentage of [...steps, 100]) {
+          await program.methods
+            .releaseFunds(percentage, null, null, null)
+            .accounts(releaseAccounts(escrowId))
+            .signers([arbiter])
+            .rpc();
+        }
+
+        const after = await Promise.all(wallets.map(balance));
+        const delivered = after.reduce((sum, value, i) => sum + value - before[i], 0);
+        assert.equal(delivered, deposit);
+        assert.equal(
+          await balance(solVaultPda(escrowId)),
+          await connection.getMinimumBalanceForRentExemption(0)
+        );
+      });
+    }
+
+    it("releases a tiny remainder of a near-complete escrow", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+      const release = (percentage: number) =>
+        program.methods
+          .releaseFunds(percentage, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+
+      // 990_000, then 9_900, then 99 leaves a single base unit
+      for (let i = 0; i < 3; i++) {
+        await release(99);
+      }
+      assert.equal((await program.account.escrow.fetch(escrow)).releasedAmount.toNumber(), 999_999);
+
+      // Half of one unit rounds to nothing
+      await expectError(release(50), "NoFundsToRelease");
+
+      await release(100);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 1_000_000);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("close grace period", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await program.methods.setCloseGracePeriod(new BN(3)).accounts(adminAccounts()).rpc();
+    });
+
+    after(async () => {
+      await program.methods.setCloseGracePeriod(new BN(0)).accounts(adminAccounts()).rpc();
+    });
+
+    it("keeps a finished escrow open until the grace period has passed", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.isAbove((await program.account.escrow.fetch(escrow)).finalizedAt.toNumber(), 0);
+
+      const closeEscrow = () =>
+        program.methods
+          .closeEscrow()
+          .accounts({
+            escrow,
+            initiator: initiator.publicKey,
+            config: configPda,
+            escrowSolVault: solVaultPda(escrowId),
+            treasury: feeWallet.publicKey,
+            escrowTokenVault: null,
+            tombstone: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([initiator])
+          .rpc();
+
+      await expectError(closeEscrow(), "GracePeriodActive");
+      await sleep(4_000);
+      await closeEscrow();
+
+      assert.isNull(await connection.getAccountInfo(escrow));
+    });
+
+    it("leaves a tombstone recording the outcome when asked to", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      const released = await program.account.escrow.fetch(escrow);
+      await sleep(4_000);
+
+      const tombstone = PublicKey.findProgramAddressSync(
+        [Buffer.from("tombstone"), escrowId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+      await program.methods
+        .closeEscrow()
+        .accounts({
+          escrow,
+          initiator: initiator.publicKey,
+          config: configPda,
+          escrowSolVault: solVaultPda(escrowId),
+          treasury: feeWallet.publicKey,
+          escrowTokenVault: null,
+          tombstone,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+
+      assert.isNull(await connection.getAccountInfo(escrow));
+      const record = await program.account.escrowTombstone.fetch(tombstone);
+      assert.equal(record.escrowId.toString(), escrowId.toString());
+      assert.deepEqual(record.status, { released: {} });
+      assert.equal(record.releasedAmount.toNumber(), 1_000_000);
+      assert.equal(record.feesPaid.toString(), released.feesPaid.toString());
+      assert.isAbove(record.feesPaid.toNumber(), 0);
+      assert.isAbove(record.closedAt.toNumber(), 0);
+    });
+  });
+
+  describe("recipient hook", () => {
+    const mockHook = anchor.workspace.MockHook as Program<MockHook>;
+    const receipt = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt")],
+      mockHook.programId
+    )[0];
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await mockHook.methods
+        .initializeReceipt()
+        .accounts({ receipt, payer: payer.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods.setHookAllowlist([]).accounts(adminAccounts()).rpc();
+    });
+
+    it("calls the recipient's allowlisted hook after paying out", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .setRecipientHook(mockHook.programId)
+        .accounts({ escrow, recipient: recipient.publicKey, config: configPda })
+        .signers([recipient])
+        .rpc();
+
+      const release = () =>
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .remainingAccounts([
+            { pubkey: mockHook.programId, isWritable: false, isSigner: false },
+            { pubkey: receipt, isWritable: true, isSigner: false },
+          ])
+          .signers([arbiter])
+          .rpc();
+
+      await expectError(release(), "InvalidRecipientHook");
+
+      await program.methods
+        .setHookAllowlist([mockHook.programId])
+        .accounts(adminAccounts())
+        .rpc();
+      await release();
+
+      const state = await mockHook.account.receipt.fetch(receipt);
+      assert.equal(state.calls.toNumber(), 1);
+      assert.equal(state.escrowId.toNumber(), escrowId.toNumber());
+      assert.equal(state.amount.toNumber(), 900_000);
+      assert.ok(state.recipient.equals(recipient.publicKey));
+    });
+  });
+
+  describe("full release", () => {
+    it("flips to Released at exact equality and rejects further releases", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const release = (percentage: number) =>
+        program.methods
+          .releaseFunds(percentage, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc();
+
+      await release(50);
+      assert.isFalse(await program.methods.isFullyReleased().accounts({ escrow }).view());
+      await release(100);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), state.amount.toNumber());
+      assert.deepEqual(state.status, { released: {} });
+      assert.isTrue(await program.methods.isFullyReleased().accounts({ escrow }).view());
+
+      await expectError(release(10), "NoFundsToRelease");
+    });
+  });
+
+  describe("two-party escrow", () => {
+    it("settles without an arbiter via a scheduled release", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", { arbiter: null });
+      assert.ok((await program.account.escrow.fetch(escrow)).arbiter.equals(PublicKey.default));
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "Unauthorized"
+      );
+
+      await program.methods
+        .scheduleRelease(new BN((await chainTime()) + 2))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await sleep(4_000);
+
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .executeScheduledRelease()
+        .accounts(releaseAccounts(escrowId, initiator.publicKey))
+        .signers([initiator])
+        .rpc();
+
+      assert.equal((await balance(recipient.publicKey)) - recipientBefore, 900_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("only lets the initiator cancel before any release", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", { arbiter: null });
+
+      await program.methods
+        .releaseFunds(50, null, null, null)
+        .accounts(releaseAccounts(escrowId, initiator.publicKey))
+        .signers([initiator])
+        .rpc();
+      await expectError(
+        program.methods
+          .cancelEscrow()
+          .accounts(cancelAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc(),
+        "InvalidEscrowStatus"
+      );
+    });
+  });
+
+  describe("total value locked", () => {
+    const tvl = async () => {
+      const details = await program.methods.getConfig().accounts({ config: configPda }).view();
+      return {
+        sol: details.totalValueLockedSol.toNumber(),
+        token: details.totalValueLockedToken.toNumber(),
+      };
+    };
+
+    it("tracks SOL funding and release", async () => {
+      const before = await tvl();
+      const { escrowId } = await createEscrow(1_000_000, "sol");
+      assert.equal((await tvl()).sol, before.sol + 1_000_000);
+
+      await program.methods
+        .releaseFunds(40, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.equal((await tvl()).sol, before.sol + 600_000);
+      assert.equal((await tvl()).token, before.token);
+    });
+
+    it("tracks FORGE funding and cancel", async () => {
+      const before = await tvl();
+      const { escrowId } = await createEscrow(2_000_000, "forge");
+      assert.equal((await tvl()).token, before.token + 2_000_000);
+
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.deepEqual(await tvl(), before);
+    });
+  });
+
+  describe("cancel with a closed initiator ATA", () => {
+    it("recreates the initiator's token account and refunds into it", async () => {
+      const owner = Keypair.generate();
+      await airdrop(owner.publicKey);
+      const ownerTokenAccount = await createAssociatedTokenAccount(
+        connection,
+        payer,
+        forgeMint,
+        owner.publicKey
+      );
+      await mintTo(connection, payer, forgeMint, ownerTokenAccount, payer, 1_000_000);
+
+      const { escrowId } = await createEscrow(1_000_000, "forge", {
+        initiator: owner,
+        initiatorTokenAccount: ownerTokenAccount,
+      });
+      await closeAccount(connection, payer, ownerTokenAccount, owner.publicKey, owner);
+      assert.isNull(await connection.getAccountInfo(ownerTokenAccount));
+
+      await program.methods
+        .cancelEscrow()
+        .accounts({
+          ...cancelAccounts(escrowId),
+          initiator: owner.publicKey,
+          initiatorTokenAccount: ownerTokenAccount,
+        })
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(Number((await getAccount(connection, ownerTokenAccount)).amount), 1_000_000);
+    });
+  });
+
+  describe("max deadline duration", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await program.methods.setMaxDeadlineDuration(new BN(3_600)).accounts(adminAccounts()).rpc();
+    });
+
+    after(async () => {
+      await program.methods.setMaxDeadlineDuration(new BN(0)).accounts(adminAccounts()).rpc();
+    });
+
+    it("accepts an expiry at the cap", async () => {
+      const expiresAt = (await chainTime()) + 3_600;
+      const { escrow } = await createEscrow(1_000_000, "sol", { expiresAt });
+      assert.equal((await program.account.escrow.fetch(escrow)).expiresAt.toNumber(), expiresAt);
+    });
+
+    it("rejects an expiry beyond the cap", async () => {
+      await expectError(
+        createEscrow(1_000_000, "sol", { expiresAt: (await chainTime()) + 3_700 }),
+        "DeadlineTooFar"
+      );
+    });
+
+    it("still allows escrows that never expire", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+      assert.equal((await program.account.escrow.fetch(escrow)).expiresAt.toNumber(), 0);
+    });
+  });
+
+  describe("pending buy-and-burn", () => {
+    const pendingBurn = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending_burn")],
+      program.programId
+    )[0];
+
+    before(async () => {
+      await program.methods
+        .initializePendingBurn()
+        .accounts({
+          pendingBurn,
+          config: configPda,
+          admin: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    it("accumulates the burn half of SOL releases", async () => {
+      const tempBefore = await balance(tempFeeWallet.publicKey);
+      const pendingBefore = await balance(pendingBurn);
+
+      for (const amount of [1_000_000, 500_000]) {
+        const { escrowId } = await createEscrow(amount);
+        await program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), pendingBurn })
+          .signers([arbiter])
+          .rpc();
+      }
+
+      assert.equal((await balance(pendingBurn)) - pendingBefore, 75_000);
+      assert.equal(await balance(tempFeeWallet.publicKey), tempBefore);
+    });
+
+    it("processes everything accumulated in one go", async () => {
+      const processAccounts = (admin: anchor.web3.PublicKey) => ({
+        pendingBurn,
+        config: configPda,
+        admin,
+        tempFeeWallet: tempFeeWallet.publicKey,
+      });
+      await expectError(
+        program.methods
+          .processPendingBurn()
+          .accounts(processAccounts(arbiter.publicKey))
+          .signers([arbiter])
+          .rpc(),
+        "Unauthorized"
+      );
+
+      const tempBefore = await balance(tempFeeWallet.publicKey);
+      await program.methods.processPendingBurn().accounts(processAccounts(payer.publicKey)).rpc();
+
+      assert.equal((await balance(tempFeeWallet.publicKey)) - tempBefore, 75_000);
+      const state = await program.account.pendingBurn.fetch(pendingBurn);
+      assert.equal(state.totalProcessed.toNumber(), 75_000);
+
+      await expectError(
+        program.methods.processPendingBurn().accounts(processAccounts(payer.publicKey)).rpc(),
+        "NothingToBurn"
+      );
+    });
+  });
+
+  describe("can_release", () => {
+    it("is true for the arbiter and initiator and false for anyone else", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+      const canRelease = (signer: anchor.web3.PublicKey) =>
+        program.methods.canRelease(signer).accounts({ escrow }).view();
+
+      assert.isTrue(await canRelease(arbiter.publicKey));
+      assert.isTrue(await canRelease(initiator.publicKey));
+      assert.isFalse(await canRelease(Keypair.generate().publicKey));
+      assert.isFalse(await canRelease(PublicKey.default));
+    });
+
+    it("lets release_funds through only for the signers can_release reports", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const stranger = Keypair.generate();
+      await airdrop(stranger.publicKey);
+      const release = (signer: Keypair) =>
+        program.methods
+          .releaseFunds(10, null, null, null)
+          .accounts(releaseAccounts(escrowId, signer.publicKey))
+          .signers([signer])
+          .rpc();
+
+      for (const signer of [recipient, stranger]) {
+        assert.isFalse(await program.methods.canRelease(signer.publicKey).accounts({ escrow }).view());
+        await expectError(release(signer), "Unauthorized");
+      }
+      for (const signer of [arbiter, initiator]) {
+        await release(signer);
+      }
+      assert.equal((await program.account.escrow.fetch(escrow)).releasedAmount.toNumber(), 190_000);
+    });
+  });
+
+  describe("deposit limit", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+    const depositor = Keypair.generate();
+    const depositorStats = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stats"), depositor.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const deposit = (amount: number) =>
+      createEscrow(amount, "sol", { initiator: depositor, userStats: depositorStats });
+
+    before(async () => {
+      await airdrop(depositor.publicKey);
+      await program.methods
+        .initializeUserStats()
+        .accounts({
+          userStats: depositorStats,
+          user: depositor.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([depositor])
+        .rpc();
+      await program.methods
+        .setDepositLimit(new BN(4), new BN(2_000_000), new BN(0))
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods
+        .setDepositLimit(new BN(0), new BN(0), new BN(0))
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    it("allows deposits up to the limit, then waits for the next window", async () => {
+      await deposit(1_500_000);
+      await deposit(500_000);
+      await expectError(deposit(1), "DepositLimitExceeded");
+      await expectError(
+        createEscrow(1_000_000, "sol", { initiator: depositor }),
+        "MissingUserStats"
+      );
+
+      await sleep(5_000);
+      await deposit(1_000_000);
+
+      const stats = await program.account.userStats.fetch(depositorStats);
+      assert.equal(stats.windowDepositedSol.toNumber(), 1_000_000);
+    });
+  });
+
+  describe("escrows by arbiter", () => {
+    it("keeps the arbiter offset in sync with the program", () => {
+      const constant = program.idl.constants.find((c) => c.name === "ESCROW_ARBITER_OFFSET");
+      assert.equal(Number(constant.value), ESCROW_ARBITER_OFFSET);
+    });
+
+    it("fetches only the escrows assigned to each arbiter", async () => {
+      const arbiterA = Keypair.generate().publicKey;
+      const arbiterB = Keypair.generate().publicKey;
+      const forA = [
+        (await createEscrow(1_000_000, "sol", { arbiter: arbiterA })).escrow,
+        (await createEscrow(1_000_000, "forge", { arbiter: arbiterA })).escrow,
+      ];
+      const forB = [(await createEscrow(1_000_000, "sol", { arbiter: arbiterB })).escrow];
+
+      const keys = async (arbiterKey: anchor.web3.PublicKey) =>
+        (await fetchEscrowsByArbiter(program, arbiterKey)).map((e) => e.publicKey.toBase58()).sort();
+      assert.deepEqual(await keys(arbiterA), forA.map((k) => k.toBase58()).sort());
+      assert.deepEqual(await keys(arbiterB), forB.map((k) => k.toBase58()).sort());
+    });
+
+    it("keeps the status offset in sync with the program", () => {
+      const constant = program.idl.constants.find((c) => c.name === "ESCROW_STATUS_OFFSET");
+      assert.equal(Number(constant.value), ESCROW_STATUS_OFFSET);
+    });
+
+    it("fetches only an arbiter's disputed escrows", async () => {
+      const arbiterA = Keypair.generate().publicKey;
+      const arbiterB = Keypair.generate().publicKey;
+      const dispute = async (escrow: anchor.web3.PublicKey) => {
+        await program.methods
+          .disputeEscrow()
+          .accounts({ escrow, party: recipient.publicKey })
+          .signers([recipient])
+          .rpc();
+        return escrow;
+      };
+
+      const disputed = [
+        await dispute((await createEscrow(1_000_000, "sol", { arbiter: arbiterA })).escrow),
+        await dispute((await createEscrow(1_000_000, "forge", { arbiter: arbiterA })).escrow),
+      ];
+      await createEscrow(1_000_000, "sol", { arbiter: arbiterA });
+      await dispute((await createEscrow(1_000_000, "sol", { arbiter: arbiterB })).escrow);
+
+      const found = (await fetchDisputedEscrowsByArbiter(program, arbiterA))
+        .map((e) => e.publicKey.toBase58())
+        .sort();
+      assert.deepEqual(found, disputed.map((k) => k.toBase58()).sort());
+    });
+  });
+
+  describe("auto-accept", () => {
+    it("releases without explicit acceptance once the window passes", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await program.methods
+        .setAcceptanceDeadline(new BN((await chainTime()) + 60))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await program.methods
+        .setAutoAcceptWindow(new BN(2))
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "NotAccepted"
+      );
+
+      await sleep(4_000);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.isFalse(state.accepted);
+      assert.deepEqual(state.status, { released: {} });
+    });
+  });
+
+  describe("fee breakdown logs", () => {
+    const logsOf = async (signature: string) => {
+      await connection.confirmTransaction(signature, "confirmed");
+      const tx = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return tx?.meta?.logMessages ?? [];
+    };
+
+    it("labels SOL fees in SOL", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const signature = await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const logs = await logsOf(signature);
+      assert.isTrue(
+        logs.some((log) => log.includes("(Sol): treasury 0.000050000 SOL, burn 0.000050000 SOL"))
+      );
+    });
+
+    it("formats FORGE fees against the mint decimals", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "forge");
+      const signature = await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const logs = await logsOf(signature);
+      assert.isTrue(
+        logs.some((log) => log.includes("(Forge): treasury 0.050000 FORGE, burn 0.050000 FORGE"))
+      );
+    });
+  });
+
+  describe("delegated deposits", () => {
+    const owner = Keypair.generate();
+    const relayer = Keypair.generate();
+    let ownerTokenAccount: anchor.web3.PublicKey;
+
+    before(async () => {
+      await airdrop(relayer.publicKey);
+      ownerTokenAccount = await createAssociatedTokenAccount(
+        connection,
+        payer,
+        forgeMint,
+        owner.publicKey
+      );
+      await mintTo(connection, payer, forgeMint, ownerTokenAccount, payer, 3_000_000);
+    });
+
+    it("lets an approved relayer fund an escrow on the owner's behalf", async () => {
+      await approve(connection, payer, ownerTokenAccount, relayer.publicKey, owner, 1_000_000);
+      const ownerLamports = await balance(owner.publicKey);
+
+      const { escrow } = await createEscrow(1_000_000, "forge", {
+        initiator: relayer,
+        tokenOwner: owner.publicKey,
+        initiatorTokenAccount: ownerTokenAccount,
+      });
+
+      const escrowAccount = await program.account.escrow.fetch(escrow);
+      assert.ok(escrowAccount.initiator.equals(owner.publicKey));
+      assert.equal(await tokenBalance(ownerTokenAccount), 2_000_000);
+      assert.equal(await balance(owner.publicKey), ownerLamports);
+    });
+
+    it("rejects a deposit larger than the approved amount", async () => {
+      await approve(connection, payer, ownerTokenAccount, relayer.publicKey, owner, 500_000);
+      await expectError(
+        createEscrow(1_000_000, "forge", {
+          initiator: relayer,
+          tokenOwner: owner.publicKey,
+          initiatorTokenAccount: ownerTokenAccount,
+        }),
+        "InsufficientDelegation"
+      );
+    });
+
+    it("rejects a signer that is not the approved delegate", async () => {
+      const stranger = Keypair.generate();
+      await airdrop(stranger.publicKey);
+      await approve(connection, payer, ownerTokenAccount, relayer.publicKey, owner, 1_000_000);
+      await expectError(
+        createEscrow(1_000_000, "forge", {
+          initiator: stranger,
+          tokenOwner: owner.publicKey,
+          initiatorTokenAccount: ownerTokenAccount,
+        }),
+        "InvalidDelegate"
+      );
+    });
+  });
+
+  describe("fee cap", () => {
+    it("caps the fee on a large release and pays the excess to the recipient", async () => {
+      const deposit = LAMPORTS_PER_SOL;
+      const feeCap = 1_000_000;
+      const { escrowId, escrow } = await createEscrow(deposit);
+      await program.methods
+        .setFeeCap(new BN(feeCap))
+        .accounts({ escrow, config: configPda, admin: payer.publicKey })
+        .rpc();
+
+      const recipientBefore = await balance(recipient.publicKey);
+      const feeWalletBefore = await balance(feeWallet.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + deposit - feeCap);
+      assert.equal(await balance(feeWallet.publicKey), feeWalletBefore + feeCap / 2);
+      assert.equal((await program.account.escrow.fetch(escrow)).feesPaid.toNumber(), feeCap);
+    });
+
+    it("only lets the admin set a fee cap", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+      await expectError(
+        program.methods
+          .setFeeCap(new BN(1))
+          .accounts({ escrow, config: configPda, admin: initiator.publicKey })
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+  });
+
+  describe("user escrow index", () => {
+    const user = Keypair.generate();
+    const userIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_index"), user.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const escrowIds: string[] = [];
+
+    before(async () => {
+      await airdrop(user.publicKey);
+      await program.methods
+        .initializeUserIndex()
+        .accounts({ userIndex, user: user.publicKey, systemProgram: SystemProgram.programId })
+        .signers([user])
+        .rpc();
+      for (let i = 0; i < 25; i++) {
+        const { escrowId } = await createEscrow(10_000, "sol", { initiator: user, userIndex });
+        escrowIds.push(escrowId.toString());
+      }
+    });
+
+    it("pages through a user's escrows", async () => {
+      const pages: string[][] = [];
+      for (let start = 0; start < 30; start += 10) {
+        const page: BN[] = await program.methods
+          .getUserEscrows(start, 10)
+          .accounts({ userIndex })
+          .view();
+        pages.push(page.map((id) => id.toString()));
+      }
+
+      assert.deepEqual(pages.map((page) => page.length), [10, 10, 5]);
+      assert.deepEqual(pages.flat(), escrowIds);
+    });
+
+    it("returns an empty page past the end", async () => {
+      const page = await program.methods.getUserEscrows(100, 10).accounts({ userIndex }).view();
+      assert.lengthOf(page, 0);
+    });
+
+    it("rejects pages larger than the maximum", async () => {
+      const err = await program.methods
+        .getUserEscrows(0, 33)
+        .accounts({ userIndex })
+        .view()
+        .then(() => null, (e) => e);
+      assert.isNotNull(err, "Expected PageTooLarge");
+      assert.include(String(err) + JSON.stringify(err), "PageTooLarge");
+    });
+
+    it("advances the per-user sequence number with each creation", async () => {
+      const nextId = async () =>
+        ((await program.methods.getNextEscrowId().accounts({ userIndex }).view()) as BN).toNumber();
+
+      assert.equal(await nextId(), escrowIds.length);
+      await createEscrow(10_000, "sol", { initiator: user, userIndex });
+      assert.equal(await nextId(), escrowIds.length + 1);
+    });
+  });
+
+  describe("templates", () => {
+    let nextTemplateId = 1;
+
+    const createTemplate = async (validUntil: number) => {
+      const templateId = new BN(nextTemplateId++);
+      const template = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("template"),
+          initiator.publicKey.toBuffer(),
+          templateId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+      await program.methods
+        .createTemplate(
+          templateId,
+          { sol: {} },
+          arbiter.publicKey,
+          recipient.publicKey,
+          false,
+          new BN(validUntil)
+        )
+        .accounts({
+          template,
+          creator: initiator.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+      return template;
+    };
+
+    it("creates an escrow on the terms of a valid template", async () => {
+      const template = await createTemplate((await chainTime()) + 3_600);
+      const { escrow } = await createEscrow(1_000_000, "sol", { template });
+
+      const escrowAccount = await program.account.escrow.fetch(escrow);
+      assert.ok(escrowAccount.recipient.equals(recipient.publicKey));
+      assert.ok(escrowAccount.arbiter.equals(arbiter.publicKey));
+      assert.deepEqual(escrowAccount.status, { funded: {} });
+      assert.equal(escrowAccount.amount.toNumber(), 1_000_000);
+    });
+
+    it("rejects an expired template", async () => {
+      const template = await createTemplate((await chainTime()) + 2);
+      await sleep(4_000);
+      await expectError(createEscrow(1_000_000, "sol", { template }), "TemplateExpired");
+    });
+  });
+
+  describe("payment splitter recipient", () => {
+    const mockSplitter = anchor.workspace.MockSplitter as Program<MockSplitter>;
+    const splitter = PublicKey.findProgramAddressSync(
+      [Buffer.from("splitter")],
+      mockSplitter.programId
+    )[0];
+    const payeeA = Keypair.generate();
+    const payeeB = Keypair.generate();
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await airdrop(payeeA.publicKey, 1);
+      await airdrop(payeeB.publicKey, 1);
+      await mockSplitter.methods
+        .createSplitter([payeeA.publicKey, payeeB.publicKey], [7_000, 3_000])
+        .accounts({ splitter, payer: payer.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+      await program.methods
+        .setHookAllowlist([mockSplitter.programId])
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods.setHookAllowlist([]).accounts(adminAccounts()).rpc();
+    });
+
+    it("pays a splitter PDA, which distributes the release to its payees", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", { recipient: splitter });
+      await mockSplitter.methods
+        .registerEscrowHook()
+        .accounts({ splitter, escrow, config: configPda, escrowProgram: program.programId })
+        .rpc();
+
+      const splitterBefore = await balance(splitter);
+      const payeeABefore = await balance(payeeA.publicKey);
+      const payeeBBefore = await balance(payeeB.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({ ...releaseAccounts(escrowId), recipient: splitter })
+        .remainingAccounts([
+          { pubkey: mockSplitter.programId, isWritable: false, isSigner: false },
+          { pubkey: payeeA.publicKey, isWritable: true, isSigner: false },
+          { pubkey: payeeB.publicKey, isWritable: true, isSigner: false },
+        ])
+        .signers([arbiter])
+        .rpc();
+
+      // 900_000 net of the 10% fee, split 70/30
+      assert.equal(await balance(payeeA.publicKey), payeeABefore + 630_000);
+      assert.equal(await balance(payeeB.publicKey), payeeBBefore + 270_000);
+      assert.equal(await balance(splitter), splitterBefore);
+      assert.deepEqual((await program.account.escrow.fetch(escrow)).status, { released: {} });
+    });
+  });
+
+  describe("self-arbitrated escrows", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    after(async () => {
+      await program.methods.setAllowSelfArbiter(false).accounts(adminAccounts()).rpc();
+    });
+
+    it("rejects the initiator as arbiter by default", async () => {
+      await expectError(
+        createEscrow(1_000_000, "sol", { arbiter: initiator.publicKey }),
+        "SelfArbiter"
+      );
+    });
+
+    it("allows the initiator as arbiter once enabled in the config", async () => {
+      await program.methods.setAllowSelfArbiter(true).accounts(adminAccounts()).rpc();
+
+      const { escrow } = await createEscrow(1_000_000, "sol", { arbiter: initiator.publicKey });
+      const escrowAccount = await program.account.escrow.fetch(escrow);
+      assert.ok(escrowAccount.arbiter.equals(initiator.publicKey));
+      assert.ok(escrowAccount.initiator.equals(initiator.publicKey));
+    });
+  });
+
+  describe("USD-amount token release", () => {
+    const mockPyth = anchor.workspace.MockPyth as Program<MockPyth>;
+    const tokenPriceFeed = Keypair.generate();
+    const PRICE_ACCOUNT_SIZE = 3312;
+
+    before(async () => {
+      const tx = new anchor.web3.Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: tokenPriceFeed.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(PRICE_ACCOUNT_SIZE),
+          space: PRICE_ACCOUNT_SIZE,
+          programId: mockPyth.programId,
+        })
+      );
+      await provider.sendAndConfirm(tx, [tokenPriceFeed]);
+      await program.methods
+        .setTokenPriceFeed(tokenPriceFeed.publicKey)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    it("releases the token equivalent of a fixed USD amount, capped at what is left", async () => {
+      const { escrowId, escrow } = await createEscrow(20_000_000, "forge");
+      const releaseUsd = (usd: number) =>
+        program.methods
+          .releaseUsd(new BN(usd * 1_000_000))
+          .accounts(releaseAccounts(escrowId))
+          .remainingAccounts([
+            { pubkey: tokenPriceFeed.publicKey, isWritable: false, isSigner: false },
+          ])
+          .signers([arbiter])
+          .rpc();
+
+      // $10 at $2/FORGE is 5 FORGE (6 decimals), 10% fee withheld
+      await mockPyth.methods
+        .setPrice(new BN(200_000_000), -8, new BN(await chainTime()))
+        .accounts({ priceFeed: tokenPriceFeed.publicKey })
+        .rpc();
+      let before = await tokenBalance(recipientTokenAccount);
+      await releaseUsd(10);
+      assert.equal((await tokenBalance(recipientTokenAccount)) - before, 4_500_000);
+
+      let state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 5_000_000);
+      assert.deepEqual(state.status, { partiallyReleased: {} });
+
+      // $100 implies 50 FORGE, but only 15 are left
+      before = await tokenBalance(recipientTokenAccount);
+      await releaseUsd(100);
+      assert.equal((await tokenBalance(recipientTokenAccount)) - before, 13_500_000);
+
+      state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("rejects USD-amount releases of SOL escrows", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      await expectError(
+        program.methods
+          .releaseUsd(new BN(1_000_000))
+          .accounts(releaseAccounts(escrowId))
+          .remainingAccounts([
+            { pubkey: tokenPriceFeed.publicKey, isWritable: false, isSigner: false },
+          ])
+          .signers([arbiter])
+          .rpc(),
+        "InvalidDealType"
+      );
+    });
+  });
+
+  describe("SOL vault reuse", () => {
+    it("closes the vault with the escrow so a reused escrow_id starts clean", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      await program.methods
+        .closeEscrow()
+        .accounts({
+          escrow,
+          initiator: initiator.publicKey,
+          config: configPda,
+          escrowSolVault: solVaultPda(escrowId),
+          treasury: feeWallet.publicKey,
+          escrowTokenVault: null,
+          tombstone: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+      assert.isNull(await connection.getAccountInfo(escrow));
+      assert.isNull(await connection.getAccountInfo(solVaultPda(escrowId)));
+
+      await createEscrow(2_000_000, "sol", { escrowId });
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.amount.toNumber(), 2_000_000);
+      assert.equal(state.releasedAmount.toNumber(), 0);
+      assert.equal(
+        await balance(solVaultPda(escrowId)),
+        (await connection.getMinimumBalanceForRentExemption(0)) + 2_000_000
+      );
+    });
+
+    it("rejects a vault that was funded before the escrow existed", async () => {
+      const escrowId = new BN(nextEscrowId++);
+      const tx = new anchor.web3.Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: payer.publicKey,
+          toPubkey: solVaultPda(escrowId),
+          lamports: LAMPORTS_PER_SOL,
+        })
+      );
+      await provider.sendAndConfirm(tx);
+
+      await expectError(createEscrow(1_000_000, "sol", { escrowId }), "VaultNotEmpty");
+    });
+
+    it("routes vault dust to the treasury when the escrow is closed", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer.publicKey,
+            toPubkey: solVaultPda(escrowId),
+            lamports: 1_234,
+          })
+        )
+      );
+
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await program.methods
+        .closeEscrow()
+        .accounts({
+          escrow,
+          initiator: initiator.publicKey,
+          config: configPda,
+          escrowSolVault: solVaultPda(escrowId),
+          treasury: feeWallet.publicKey,
+          escrowTokenVault: null,
+          tombstone: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([initiator])
+        .rpc();
+
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 1_234);
+      assert.isNull(await connection.getAccountInfo(solVaultPda(escrowId)));
+    });
+  });
+
+  describe("cooling-off window", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    const pauseByRecipient = (escrow: anchor.web3.PublicKey) =>
+      program.methods
+        .pauseEscrow()
+        .accounts({ escrow, party: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+
+    const cancelByInitiator = (escrowId: BN) =>
+      program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId, initiator.publicKey))
+        .signers([initiator])
+        .rpc();
+
+    before(async () => {
+      await program.methods.setCoolingOffPeriod(new BN(3)).accounts(adminAccounts()).rpc();
+    });
+
+    after(async () => {
+      await program.methods.setCoolingOffPeriod(new BN(0)).accounts(adminAccounts()).rpc();
+    });
+
+    it("lets the initiator cancel a paused escrow for a full refund inside the window", async () => {
+      const before = await balance(initiator.publicKey);
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await pauseByRecipient(escrow);
+
+      await cancelByInitiator(escrowId);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { cancelled: {} });
+      // Only the rent of the escrow's accounts is still out
+      const rent =
+        (await connection.getBalance(escrow)) +
+        (await connection.getBalance(solVaultPda(escrowId))) +
+        (await connection.getBalance(tokenVault(escrowId)));
+      assert.equal(await balance(initiator.publicKey), before - rent);
+    });
+
+    it("applies the normal cancel rules once the window has passed", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await pauseByRecipient(escrow);
+      await sleep(4_000);
+
+      await expectError(cancelByInitiator(escrowId), "EscrowPaused");
+    });
+
+    it("blocks cancelling a disputed escrow, even inside the window", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .disputeEscrow()
+        .accounts({ escrow, party: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+
+      await expectError(cancelByInitiator(escrowId), "EscrowDisputed");
+      await expectError(
+        program.methods
+          .cancelEscrow()
+          .accounts(cancelAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "EscrowDisputed"
+      );
+      const state = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(state.status, { disputed: {} });
+    });
+  });
+
+  describe("alternate token programs", () => {
+    let mint2022: anchor.web3.PublicKey;
+    let initiator2022: anchor.web3.PublicKey;
+    let recipient2022: anchor.web3.PublicKey;
+    let fee2022: anchor.web3.PublicKey;
+    let burn2022: anchor.web3.PublicKey;
+
+    const ata2022 = (owner: anchor.web3.PublicKey) =>
+      createAssociatedTokenAccount(
+        connection,
+        payer,
+        mint2022,
+        owner,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    before(async () => {
+      mint2022 = await createMint(
+        connection,
+        payer,
+        payer.publicKey,
+        null,
+        6,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      initiator2022 = await ata2022(initiator.publicKey);
+      recipient2022 = await ata2022(recipient.publicKey);
+      fee2022 = await ata2022(feeWallet.publicKey);
+      burn2022 = await ata2022(burnWallet.publicKey);
+      await mintTo(
+        connection,
+        payer,
+        mint2022,
+        initiator2022,
+        payer,
+        10_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+    });
+
+    after(async () => {
+      await program.methods
+        .setTokenProgramAllowlist([])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    const create2022 = () =>
+      createEscrow(1_000_000, "forge", {
+        initiatorTokenAccount: initiator2022,
+        mint: mint2022,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      });
+
+    it("rejects a token program that is not allowlisted", async () => {
+      await expectError(create2022(), "TokenProgramNotAllowed");
+    });
+
+    it("funds and releases a Token-2022 escrow once allowlisted", async () => {
+      await program.methods
+        .setTokenProgramAllowlist([TOKEN_2022_PROGRAM_ID])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+
+      const { escrowId, escrow } = await create2022();
+      assert.ok(
+        (await program.account.escrow.fetch(escrow)).tokenProgram.equals(TOKEN_2022_PROGRAM_ID)
+      );
+
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({
+          ...releaseAccounts(escrowId),
+          escrowTokenVault: getAssociatedTokenAddressSync(
+            mint2022,
+            escrow,
+            true,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          recipientTokenAccount: recipient2022,
+          feeWalletTokenAccount: fee2022,
+          burnTokenAccount: burn2022,
+          forgeMint: mint2022,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([arbiter])
+        .rpc();
+
+      const received = await getAccount(connection, recipient2022, undefined, TOKEN_2022_PROGRAM_ID);
+      const fees = await getAccount(connection, fee2022, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal(Number(received.amount), 900_000);
+      assert.equal(Number(fees.amount), 50_000);
+    });
+  });
+
+  describe("transfer-hook mints", () => {
+    const mockTransferHook = anchor.workspace.MockTransferHook as Program<MockTransferHook>;
+    const hookMint = Keypair.generate();
+    const [counter] = PublicKey.findProgramAddressSync(
+      [Buffer.from("counter")],
+      mockTransferHook.programId
+    );
+    const [extraAccountMetas] = PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-account-metas"), hookMint.publicKey.toBuffer()],
+      mockTransferHook.programId
+    );
+    // What the escrow forwards to Token-2022, which resolves the counter from the list
+    const hookAccounts = [
+      { pubkey: extraAccountMetas, isSigner: false, isWritable: false },
+      { pubkey: counter, isSigner: false, isWritable: true },
+      { pubkey: mockTransferHook.programId, isSigner: false, isWritable: false },
+    ];
+    const hookAta = (owner: anchor.web3.PublicKey) =>
+      getAssociatedTokenAddressSync(hookMint.publicKey, owner, true, TOKEN_2022_PROGRAM_ID);
+    const transfers = async () =>
+      (await mockTransferHook.account.counter.fetch(counter)).transfers.toNumber();
+
+    before(async () => {
+      const mintLen = getMintLen([ExtensionType.TransferHook]);
+      const tx = new anchor.web3.Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: hookMint.publicKey,
+          space: mintLen,
+          lamports: await connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferHookInstruction(
+          hookMint.publicKey,
+          payer.publicKey,
+          mockTransferHook.programId,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(hookMint.publicKey, 6, payer.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      );
+      await provider.sendAndConfirm(tx, [hookMint]);
+
+      await mockTransferHook.methods
+        .initializeCounter()
+        .accounts({ payer: payer.publicKey, counter })
+        .rpc();
+      await mockTransferHook.methods
+        .initializeExtraAccountMetas()
+        .accounts({ payer: payer.publicKey, extraAccountMetas, mint: hookMint.publicKey, counter })
+        .rpc();
+
+      for (const owner of [initiator.publicKey, recipient.publicKey, feeWallet.publicKey, burnWallet.publicKey]) {
+        await createAssociatedTokenAccount(
+          connection,
+          payer,
+          hookMint.publicKey,
+          owner,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+      }
+      await mintTo(
+        connection,
+        payer,
+        hookMint.publicKey,
+        hookAta(initiator.publicKey),
+        payer,
+        10_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      await program.methods
+        .setTokenProgramAllowlist([TOKEN_2022_PROGRAM_ID])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods
+        .setTokenProgramAllowlist([])
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    });
+
+    const createHooked = () =>
+      createEscrow(1_000_000, "forge", {
+        initiatorTokenAccount: hookAta(initiator.publicKey),
+        mint: hookMint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        remainingAccounts: hookAccounts,
+      });
+
+    it("runs the hook on funding and on each release transfer", async () => {
+      const before = await transfers();
+      const { escrowId, escrow } = await createHooked();
+      assert.equal(await transfers(), before + 1);
+
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({
+          ...releaseAccounts(escrowId),
+          escrowTokenVault: hookAta(escrow),
+          recipientTokenAccount: hookAta(recipient.publicKey),
+          feeWalletTokenAccount: hookAta(feeWallet.publicKey),
+          burnTokenAccount: hookAta(burnWallet.publicKey),
+          forgeMint: hookMint.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .remainingAccounts(hookAccounts)
+        .signers([arbiter])
+        .rpc();
+
+      // Recipient, fee wallet and burn
+      assert.equal(await transfers(), before + 4);
+      const received = await getAccount(
+        connection,
+        hookAta(recipient.publicKey),
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(Number(received.amount), 900_000);
+    });
+
+    it("fails the release without the hook accounts", async () => {
+      const { escrowId, escrow } = await createHooked();
+
+      // Token-2022 rejects the transfer before the escrow sees an error of its own
+      let failed = false;
+      try {
+        await program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts({
+            ...releaseAccounts(escrowId),
+            escrowTokenVault: hookAta(escrow),
+            recipientTokenAccount: hookAta(recipient.publicKey),
+            feeWalletTokenAccount: hookAta(feeWallet.publicKey),
+            burnTokenAccount: hookAta(burnWallet.publicKey),
+            forgeMint: hookMint.publicKey,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([arbiter])
+          .rpc();
+      } catch {
+        failed = true;
+      }
+      assert.isTrue(failed);
+      assert.equal((await program.account.escrow.fetch(escrow)).releasedAmount.toNumber(), 0);
+    });
+
+    it("refunds through the hook on cancel", async () => {
+      const { escrowId, escrow } = await createHooked();
+      const initiatorBefore = await getAccount(
+        connection,
+        hookAta(initiator.publicKey),
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      const before = await transfers();
+
+      await program.methods
+        .cancelEscrow()
+        .accounts({
+          ...cancelAccounts(escrowId),
+          escrowTokenVault: hookAta(escrow),
+          initiatorTokenAccount: hookAta(initiator.publicKey),
+          forgeMint: hookMint.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .remainingAccounts(hookAccounts)
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await transfers(), before + 1);
+      const initiatorAfter = await getAccount(
+        connection,
+        hookAta(initiator.publicKey),
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(Number(initiatorAfter.amount - initiatorBefore.amount), 1_000_000);
+    });
+  });
+
+  describe("release requests", () => {
+    const requestRelease = (escrow: anchor.web3.PublicKey, percentage: number) =>
+      program.methods
+        .requestRelease(percentage)
+        .accounts({ escrow, recipient: recipient.publicKey, config: configPda })
+        .signers([recipient])
+        .rpc();
+
+    it("releases what the recipient requested once the arbiter approves", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await requestRelease(escrow, 40);
+      assert.equal((await program.account.escrow.fetch(escrow)).pendingRelease, 40);
+
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .approvePendingRelease()
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 360_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 400_000);
+      assert.equal(state.pendingRelease, 0);
+      assert.deepEqual(state.status, { partiallyReleased: {} });
+    });
+
+    it("only lets the arbiter approve", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await requestRelease(escrow, 40);
+
+      await expectError(
+        program.methods
+          .approvePendingRelease()
+          .accounts(releaseAccounts(escrowId, initiator.publicKey))
+          .signers([initiator])
+          .rpc(),
+        "Unauthorized"
+      );
+    });
+
+    it("clears a rejected request so it cannot be approved", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await requestRelease(escrow, 40);
+
+      await program.methods
+        .rejectPendingRelease()
+        .accounts({ escrow, arbiter: arbiter.publicKey })
+        .signers([arbiter])
+        .rpc();
+      assert.equal((await program.account.escrow.fetch(escrow)).pendingRelease, 0);
+
+      await expectError(
+        program.methods
+          .approvePendingRelease()
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "NoPendingRelease"
+      );
+      assert.equal((await program.account.escrow.fetch(escrow)).releasedAmount.toNumber(), 0);
+    });
+  });
+
+  describe("PDA bumps", () => {
+    it("returns the same bumps clients derive for an escrow's accounts", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+
+      const bumps = await program.methods
+        .getBumps(escrowId)
+        .accounts({ forgeMint })
+        .view();
+
+      const [, escrowBump] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), escrowId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const [, solVaultBump] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_vault"), escrowId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const [vault, tokenVaultBump] = PublicKey.findProgramAddressSync(
+        [escrow.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), forgeMint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+
+      assert.ok(vault.equals(tokenVault(escrowId)));
+      assert.equal(bumps.escrow, escrowBump);
+      assert.equal(bumps.solVault, solVaultBump);
+      assert.equal(bumps.tokenVault, tokenVaultBump);
+      assert.equal((await program.account.escrow.fetch(escrow)).solVaultBump, solVaultBump);
+    });
+  });
+
+  describe("insurance pool", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+    const insurancePool = PublicKey.findProgramAddressSync(
+      [Buffer.from("insurance_pool")],
+      program.programId
+    )[0];
+
+    before(async () => {
+      await program.methods
+        .initializeInsurancePool()
+        .accounts({ insurancePool, ...adminAccounts(), systemProgram: SystemProgram.programId })
+        .rpc();
+      await program.methods.setInsuranceBps(1_000).accounts(adminAccounts()).rpc();
+    });
+
+    after(async () => {
+      await program.methods.setInsuranceBps(0).accounts(adminAccounts()).rpc();
+    });
+
+    it("accumulates the insurance slice of the fee on each release", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const poolBefore = await balance(insurancePool);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+
+      for (const percentage of [50, 100]) {
+        await program.methods
+          .releaseFunds(percentage, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), insurancePool })
+          .signers([arbiter])
+          .rpc();
+        // 10% of each 50_000 fee goes to the pool
+        assert.equal((await balance(insurancePool)) - poolBefore, percentage === 50 ? 5_000 : 10_000);
+      }
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 45_000);
+    });
+
+    it("takes the insurance slice of a settlement fee", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const poolBefore = await balance(insurancePool);
+
+      await program.methods
+        .settle(4_000)
+        .accounts({ ...settleAccounts(escrowId), insurancePool })
+        .signers([arbiter])
+        .rpc();
+      // 10% of the 40_000 fee on the recipient's share
+      assert.equal((await balance(insurancePool)) - poolBefore, 4_000);
+    });
+
+    it("requires the pool while an insurance share is configured", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "MissingInsurancePool"
+      );
+    });
+
+    it("holds an announced claim until its timelock passes", async () => {
+      const claimAccounts = {
+        insurancePool,
+        ...adminAccounts(),
+        destination: feeWallet.publicKey,
+      };
+      await program.methods.requestInsuranceClaim(new BN(5_000)).accounts(claimAccounts).rpc();
+
+      const state = await program.account.insurancePool.fetch(insurancePool);
+      assert.equal(state.claimAmount.toNumber(), 5_000);
+      assert.isAbove(state.claimUnlocksAt.toNumber(), await chainTime());
+
+      await expectError(
+        program.methods.claimInsurance().accounts(claimAccounts).rpc(),
+        "InsuranceClaimLocked"
+      );
+    });
+  });
+
+  describe("release conditions", () => {
+    const mockTracker = anchor.workspace.MockTracker as Program<MockTracker>;
+    const shipment = Keypair.generate();
+
+    const setDelivered = (delivered: boolean) =>
+      mockTracker.methods
+        .setDelivered(delivered)
+        .accounts({ shipment: shipment.publicKey, carrier: payer.publicKey })
+        .rpc();
+
+    before(async () => {
+      await mockTracker.methods
+        .createShipment()
+        .accounts({
+          shipment: shipment.publicKey,
+          carrier: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([shipment])
+        .rpc();
+    });
+
+    it("releases only while the tracked shipment is marked delivered", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", {
+        // The delivered flag sits right after the 8-byte discriminator
+        releaseCondition: { account: shipment.publicKey, offset: 8, expected: 1 },
+      });
+      const release = () =>
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts({ ...releaseAccounts(escrowId), conditionAccount: shipment.publicKey })
+          .signers([arbiter])
+          .rpc();
+
+      await expectError(release(), "ReleaseConditionNotMet");
+
+      await setDelivered(true);
+      await release();
+      assert.equal((await program.account.escrow.fetch(escrow)).releasedAmount.toNumber(), 500_000);
+
+      await setDelivered(false);
+      await expectError(release(), "ReleaseConditionNotMet");
+    });
+
+    it("rejects a release without the condition account", async () => {
+      const { escrowId } = await createEscrow(1_000_000, "sol", {
+        releaseCondition: { account: shipment.publicKey, offset: 8, expected: 1 },
+      });
+
+      await expectError(
+        program.methods
+          .releaseFunds(50, null, null, null)
+          .accounts(releaseAccounts(escrowId))
+          .signers([arbiter])
+          .rpc(),
+        "MissingConditionAccount"
+      );
+    });
+
+    it("holds milestone releases to the condition too", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "sol", {
+        releaseCondition: { account: shipment.publicKey, offset: 8, expected: 1 },
+      });
+      await program.methods
+        .setMilestones([new BN(1_000_000)])
+        .accounts({ escrow, initiator: initiator.publicKey })
+        .signers([initiator])
+        .rpc();
+      await setDelivered(false);
+
+      await expectError(
+        program.methods
+          .releaseMilestone(0)
+          .accounts({ ...releaseAccounts(escrowId), conditionAccount: shipment.publicKey })
+          .signers([arbiter])
+          .rpc(),
+        "ReleaseConditionNotMet"
+      );
+    });
+  });
+
+  describe("batch escrow details", () => {
+    it("returns the details of several escrows in one call", async () => {
+      const escrows = [];
+      for (let i = 1; i <= 5; i++) {
+        escrows.push(await createEscrow(i * 1_000_000));
+      }
+
+      const details = await program.methods
+        .getEscrowDetailsBatch()
+        .accounts({ config: configPda })
+        .remainingAccounts(
+          escrows.map(({ escrow }) => ({ pubkey: escrow, isWritable: false, isSigner: false }))
+        )
+        .view();
+
+      assert.equal(details.length, 5);
+      details.forEach((detail, i) => {
+        assert.ok(detail.escrowId.eq(escrows[i].escrowId));
+        assert.equal(detail.amount.toNumber(), (i + 1) * 1_000_000);
+        assert.deepEqual(detail.status, { funded: {} });
+      });
+    });
+  });
+
+  describe("dust threshold", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    before(async () => {
+      await program.methods
+        .setDustThreshold(new BN(20_000), new BN(0))
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    after(async () => {
+      await program.methods.setDustThreshold(new BN(0), new BN(0)).accounts(adminAccounts()).rpc();
+    });
+
+    const release = (escrowId: BN, percentage: number) =>
+      program.methods
+        .releaseFunds(percentage, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+    it("sweeps a sub-threshold remainder to the recipient and finalizes", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const recipientBefore = await balance(recipient.publicKey);
+
+      // 99% would leave 10_000 lamports, under the threshold
+      await release(escrowId, 99);
+
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 900_000);
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 1_000_000);
+      assert.deepEqual(state.status, { released: {} });
+    });
+
+    it("leaves remainders at or above the threshold in place", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      await release(escrowId, 98);
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.equal(state.releasedAmount.toNumber(), 980_000);
+      assert.deepEqual(state.status, { partiallyReleased: {} });
+    });
+  });
+
+  describe("CPI-created escrows", () => {
+    const mockCreator = anchor.workspace.MockCreator as Program<MockCreator>;
+    const creator = PublicKey.findProgramAddressSync(
+      [Buffer.from("creator")],
+      mockCreator.programId
+    )[0];
+
+    before(async () => {
+      await airdrop(creator);
+    });
+
+    it("records a calling program's PDA as the initiator", async () => {
+      const escrowId = new BN(nextEscrowId++);
+      const escrow = escrowPda(escrowId);
+      const creatorBefore = await balance(creator);
+
+      await mockCreator.methods
+        .createEscrow(escrowId, new BN(1_000_000), arbiter.publicKey, recipient.publicKey)
+        .accounts({
+          creator,
+          escrow,
+          config: configPda,
+          escrowSolVault: solVaultPda(escrowId),
+          escrowTokenVault: tokenVault(escrowId),
+          initiatorTokenAccount,
+          forgeMint,
+          recipientWhitelist: recipientWhitelistPda(creator),
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          escrowProgram: program.programId,
+        })
+        .rpc();
+
+      const state = await program.account.escrow.fetch(escrow);
+      assert.ok(state.initiator.equals(creator));
+      assert.ok(state.recipient.equals(recipient.publicKey));
+      assert.equal(state.amount.toNumber(), 1_000_000);
+      assert.isBelow(await balance(creator), creatorBefore - 1_000_000);
+
+      // The escrow behaves like any other from here on
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      assert.equal(await balance(recipient.publicKey), recipientBefore + 900_000);
+    });
+  });
+
+  describe("payout assignment", () => {
+    const assignee = Keypair.generate();
+
+    const assignPayout = (escrow: anchor.web3.PublicKey, signer: Keypair, newRecipient: PublicKey) =>
+      program.methods
+        .assignPayout(newRecipient)
+        .accounts({
+          escrow,
+          recipient: signer.publicKey,
+          recipientWhitelist: recipientWhitelistPda(initiator.publicKey),
+        })
+        .signers([signer])
+        .rpc();
+
+    before(async () => {
+      await airdrop(assignee.publicKey);
+    });
+
+    it("pays the remaining releases to the assignee", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .releaseFunds(50, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      await assignPayout(escrow, recipient, assignee.publicKey);
+      assert.ok((await program.account.escrow.fetch(escrow)).recipient.equals(assignee.publicKey));
+
+      // The original recipient no longer holds the payout rights
+      await expectError(assignPayout(escrow, recipient, recipient.publicKey), "Unauthorized");
+
+      const assigneeBefore = await balance(assignee.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts({ ...releaseAccounts(escrowId), recipient: assignee.publicKey })
+        .signers([arbiter])
+        .rpc();
+      assert.equal(await balance(assignee.publicKey), assigneeBefore + 450_000);
+    });
+
+    it("cannot assign a disputed escrow", async () => {
+      const { escrow } = await createEscrow(1_000_000);
+      await program.methods
+        .disputeEscrow()
+        .accounts({ escrow, party: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+
+      await expectError(
+        assignPayout(escrow, recipient, assignee.publicKey),
+        "InvalidEscrowStatus"
+      );
+    });
+  });
+
+  describe("mint allowlist", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+    const isMintAllowed = (mint: PublicKey) =>
+      program.methods.isMintAllowed(mint).accounts({ config: configPda }).view();
+
+    after(async () => {
+      await program.methods.setMintAllowlist([]).accounts(adminAccounts()).rpc();
+    });
+
+    it("reports whether a mint is allowed", async () => {
+      const otherMint = Keypair.generate().publicKey;
+      assert.isTrue(await isMintAllowed(otherMint));
+
+      await program.methods.setMintAllowlist([forgeMint]).accounts(adminAccounts()).rpc();
+
+      assert.isTrue(await isMintAllowed(forgeMint));
+      assert.isFalse(await isMintAllowed(otherMint));
+    });
+
+    it("rejects token escrows in a mint that is not listed", async () => {
+      const otherMint = await createMint(connection, payer, payer.publicKey, null, 6);
+      const otherTokenAccount = await createAssociatedTokenAccount(
+        connection,
+        payer,
+        otherMint,
+        initiator.publicKey
+      );
+      await program.methods.setMintAllowlist([forgeMint]).accounts(adminAccounts()).rpc();
+
+      await expectError(
+        createEscrow(1_000_000, "forge", {
+          initiatorTokenAccount: otherTokenAccount,
+          mint: otherMint,
+        }),
+        "MintNotAllowed"
+      );
+    });
+  });
+
+  describe("time-weighted fee discount", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+    const setFeeDiscount = (window: number, maxDiscountBps: number) =>
+      program.methods
+        .setFeeDiscount(new BN(window), maxDiscountBps)
+        .accounts(adminAccounts())
+        .rpc();
+
+    const releaseAll = async (escrowId: BN) => {
+      const recipientBefore = await balance(recipient.publicKey);
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      return (await balance(recipient.publicKey)) - recipientBefore;
+    };
+
+    after(async () => {
+      await setFeeDiscount(0, 0);
+    });
+
+    it("charges the full fee on an immediate release", async () => {
+      await setFeeDiscount(30 * 24 * 60 * 60, 5_000);
+      const { escrowId } = await createEscrow(1_000_000);
+
+      assert.equal(await releaseAll(escrowId), 900_000);
+    });
+
+    it("halves the fee once the discount window has passed", async () => {
+      await setFeeDiscount(2, 5_000);
+      const { escrowId } = await createEscrow(1_000_000);
+      await sleep(3_000);
+
+      assert.equal(await releaseAll(escrowId), 950_000);
+    });
+  });
+
+  describe("state snapshots", () => {
+    const snapshot = async (escrow: anchor.web3.PublicKey) => {
+      const signature = await program.methods.snapshotEscrow().accounts({ escrow }).rpc();
+      const event = (await eventsOf(signature)).find((e) => e.name === "StateSnapshot");
+      assert.ok(event, "Expected a StateSnapshot event");
+      assert.ok(event.data.escrow.equals(escrow));
+      return {
+        hash: Buffer.from(event.data.stateHash as number[]).toString("hex"),
+        slot: (event.data.slot as BN).toNumber(),
+      };
+    };
+
+    it("commits to the current state and changes as it evolves", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+
+      const first = await snapshot(escrow);
+
+      await program.methods
+        .releaseFunds(40, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      const afterRelease = await snapshot(escrow);
+      assert.notEqual(afterRelease.hash, first.hash);
+      assert.isAbove(afterRelease.slot, first.slot);
+    });
+  });
+
+  describe("recipient whitelist", () => {
+    const buyer = Keypair.generate();
+    const vendor = Keypair.generate().publicKey;
+    const setWhitelist = (enabled: boolean, recipients: anchor.web3.PublicKey[]) =>
+      program.methods
+        .setRecipientWhitelist(enabled, recipients)
+        .accounts({
+          recipientWhitelist: recipientWhitelistPda(buyer.publicKey),
+          owner: buyer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+    before(async () => {
+      await airdrop(buyer.publicKey);
+    });
+
+    it("only lets the initiator's escrows pay whitelisted recipients while enabled", async () => {
+      await setWhitelist(true, [vendor]);
+
+      await createEscrow(100_000, "sol", { initiator: buyer, recipient: vendor });
+      await expectError(
+        createEscrow(100_000, "sol", { initiator: buyer, recipient: recipient.publicKey }),
+        "RecipientNotWhitelisted"
+      );
+
+      await setWhitelist(false, [vendor]);
+      const { escrow } = await createEscrow(100_000, "sol", {
+        initiator: buyer,
+        recipient: recipient.publicKey,
+      });
+      assert.ok((await program.account.escrow.fetch(escrow)).recipient.equals(recipient.publicKey));
+    });
+
+    it("keeps payouts from being assigned to a wallet off the whitelist", async () => {
+      await setWhitelist(true, [recipient.publicKey]);
+      const { escrow } = await createEscrow(100_000, "sol", {
+        initiator: buyer,
+        recipient: recipient.publicKey,
+      });
+      const assignPayout = (newRecipient: anchor.web3.PublicKey) =>
+        program.methods
+          .assignPayout(newRecipient)
+          .accounts({
+            escrow,
+            recipient: recipient.publicKey,
+            recipientWhitelist: recipientWhitelistPda(buyer.publicKey),
+          })
+          .signers([recipient])
+          .rpc();
+
+      await expectError(assignPayout(Keypair.generate().publicKey), "RecipientNotWhitelisted");
+
+      await setWhitelist(true, [recipient.publicKey, vendor]);
+      await assignPayout(vendor);
+      assert.ok((await program.account.escrow.fetch(escrow)).recipient.equals(vendor));
+    });
+  });
+
+  describe("arbiter fee override", () => {
+    it("lets only the arbiter charge a negotiated fee within the cap", async () => {
+      const { escrowId } = await createEscrow(1_000_000);
+      const release = (signer: anchor.web3.Keypair, feeBps: number) =>
+        program.methods
+          .releaseFunds(100, null, null, feeBps)
+          .accounts(releaseAccounts(escrowId, signer.publicKey))
+          .signers([signer])
+          .rpc();
+
+      await expectError(release(initiator, 0), "Unauthorized");
+      await expectError(release(arbiter, 1_001), "FeeTooHigh");
+
+      const recipientBefore = await balance(recipient.publicKey);
+      const treasuryBefore = await balance(feeWallet.publicKey);
+      await release(arbiter, 200);
+
+      assert.equal((await balance(recipient.publicKey)) - recipientBefore, 980_000);
+      assert.equal((await balance(feeWallet.publicKey)) - treasuryBefore, 10_000);
+    });
+  });
+
+  describe("close readiness", () => {
+    it("reports a funded escrow as not closable and a cancelled, emptied one as closable", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const canClose = () =>
+        program.methods
+          .canClose()
+          .accounts({
+            escrow,
+            config: configPda,
+            escrowTokenVault: null,
+          })
+          .view();
+
+      assert.isFalse(await canClose());
+
+      await program.methods
+        .cancelEscrow()
+        .accounts(cancelAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+
+      assert.isTrue(await canClose());
+    });
+
+    it("refuses to close a FORGE escrow that still holds tokens", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000, "forge");
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(escrowId))
+        .signers([arbiter])
+        .rpc();
+      await mintTo(connection, payer, forgeMint, tokenVault(escrowId), payer, 1_000);
+
+      const closeAccounts = {
+        escrow,
+        initiator: initiator.publicKey,
+        config: configPda,
+        escrowSolVault: solVaultPda(escrowId),
+        treasury: feeWallet.publicKey,
+        escrowTokenVault: tokenVault(escrowId),
+        tombstone: null,
+        systemProgram: SystemProgram.programId,
+      };
+      assert.isFalse(
+        await program.methods
+          .canClose()
+          .accounts({ escrow, config: configPda, escrowTokenVault: tokenVault(escrowId) })
+          .view()
+      );
+      await expectError(
+        program.methods.closeEscrow().accounts(closeAccounts).signers([initiator]).rpc(),
+        "VaultNotEmpty"
+      );
+    });
+  });
+
+  describe("cancel and reinit", () => {
+    const adminAccounts = () => ({ config: configPda, admin: payer.publicKey });
+
+    const cancelAndReinit = (escrowId: BN, newEscrowId: BN, newArbiter: anchor.web3.PublicKey) => {
+      dealTypes.set(newEscrowId.toNumber(), "sol");
+      return program.methods
+        .cancelAndReinit(newEscrowId, newArbiter, recipient.publicKey, new BN(0))
+        .accounts({
+          escrow: escrowPda(escrowId),
+          newEscrow: escrowPda(newEscrowId),
+          initiator: initiator.publicKey,
+          config: configPda,
+          userStats: null,
+          userIndex: null,
+          recipientWhitelist: recipientWhitelistPda(initiator.publicKey),
+          escrowSolVault: solVaultPda(escrowId),
+          newEscrowSolVault: solVaultPda(newEscrowId),
+          escrowTokenVault: tokenVault(escrowId),
+          newEscrowTokenVault: tokenVault(newEscrowId),
+          forgeMint,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .signers([initiator])
+        .rpc();
+    };
+
+    after(async () => {
+      await program.methods.setPermissioned(false).accounts(adminAccounts()).rpc();
+      await program.methods
+        .addAllowedInitiator(initiator.publicKey)
+        .accounts(adminAccounts())
+        .rpc();
+    });
+
+    it("moves an escrow's funds into a new escrow under a new arbiter", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      const newArbiter = anchor.web3.Keypair.generate();
+      const newEscrowId = new BN(nextEscrowId++);
+      const newEscrow = escrowPda(newEscrowId);
+
+      const oldVaultBefore = await balance(solVaultPda(escrowId));
+      const initiatorBefore = await balance(initiator.publicKey);
+      await cancelAndReinit(escrowId, newEscrowId, newArbiter.publicKey);
+
+      const old = await program.account.escrow.fetch(escrow);
+      assert.deepEqual(old.status, { cancelled: {} });
+      const next = await program.account.escrow.fetch(newEscrow);
+      assert.deepEqual(next.status, { funded: {} });
+      assert.equal(next.amount.toNumber(), 1_000_000);
+      assert.equal(next.arbiter.toBase58(), newArbiter.publicKey.toBase58());
+      assert.equal(next.initiator.toBase58(), initiator.publicKey.toBase58());
+
+      // The funds went vault to vault; the initiator only paid rent for the new accounts
+      assert.equal(await balance(solVaultPda(escrowId)), oldVaultBefore - 1_000_000);
+      const newVaultRent = await connection.getMinimumBalanceForRentExemption(0);
+      assert.equal(await balance(solVaultPda(newEscrowId)), newVaultRent + 1_000_000);
+      assert.isBelow(await balance(initiator.publicKey), initiatorBefore);
+
+      await expectError(
+        program.methods
+          .releaseFunds(100, null, null, null)
+          .accounts(releaseAccounts(newEscrowId))
+          .signers([arbiter])
+          .rpc(),
+        "Unauthorized"
+      );
+      await program.methods
+        .releaseFunds(100, null, null, null)
+        .accounts(releaseAccounts(newEscrowId, newArbiter.publicKey))
+        .signers([newArbiter])
+        .rpc();
+      assert.deepEqual((await program.account.escrow.fetch(newEscrow)).status, { released: {} });
+    });
 
-  const program = anchor.workspace.Esscrow as Program<Esscrow>;
+    it("holds the new escrow to the creation rules, like a de-listed initiator", async () => {
+      const { escrowId, escrow } = await createEscrow(1_000_000);
+      await program.methods.setPermissioned(true).accounts(adminAccounts()).rpc();
+      await program.methods
+        .removeAllowedInitiator(initiator.publicKey)
+        .accounts(adminAccounts())
+        .rpc();
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+      const newEscrowId = new BN(nextEscrowId++);
+      await expectError(
+        cancelAndReinit(escrowId, newEscrowId, Keypair.generate().publicKey),
+        "InitiatorNotAllowed"
+      );
+      assert.deepEqual((await program.account.escrow.fetch(escrow)).status, { funded: {} });
+    });
   });
 });